@@ -0,0 +1,359 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    io::{self, Read},
+};
+
+use chrono::{DateTime, Utc};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde_json::{json, Value};
+use tar::{Archive, Builder, Header};
+
+use super::{
+    backup::Backup,
+    compression::content_hash,
+    encryption::{self, DecryptionError, Encryption},
+};
+
+const MANIFEST_PATH: &str = "manifest.json";
+const MANIFEST_VERSION: u64 = 1;
+const TIMESTAMP_FORMAT: &str = "%Y%m%dT%H%M%S%.9fZ";
+
+#[derive(Debug)]
+pub enum ArchiveError {
+    IoError(io::Error),
+    MissingManifest,
+    InvalidManifest(String),
+    MissingSave(String),
+    /// The save does not match the hash in the manifest
+    CorruptedSave(String),
+    DecryptionFailed(String, DecryptionError),
+}
+
+impl fmt::Display for ArchiveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::IoError(error) => write!(f, "the archive cannot be read: {}", error),
+            Self::MissingManifest => write!(f, "the archive has no {}", MANIFEST_PATH),
+            Self::InvalidManifest(reason) => write!(f, "invalid manifest: {}", reason),
+            Self::MissingSave(path) => write!(f, "{} is missing from the archive", path),
+            Self::CorruptedSave(path) => write!(f, "{} does not match its hash", path),
+            Self::DecryptionFailed(path, error) => write!(f, "{}: {}", path, error),
+        }
+    }
+}
+
+pub type ArchiveResult<T> = Result<T, ArchiveError>;
+
+fn save_path(backup: &Backup) -> String {
+    format!(
+        "saves/{}_{}.txt",
+        backup.saved_at.format(TIMESTAMP_FORMAT),
+        backup.id.unwrap_or_default()
+    )
+}
+
+fn append_file(
+    builder: &mut Builder<GzEncoder<Vec<u8>>>,
+    path: &str,
+    contents: &[u8],
+    modified_at: DateTime<Utc>,
+) -> io::Result<()> {
+    let mut header = Header::new_gnu();
+    header.set_size(contents.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(modified_at.timestamp().max(0) as u64);
+
+    builder.append_data(&mut header, path, contents)
+}
+
+/// Pack backups in a `.tar.gz` archive
+///
+/// Each save is stored as a text file, encrypted and bound to its path when
+/// backups are encrypted, and `manifest.json` lists them along with the
+/// metadata needed to import them back.
+pub fn export_archive(
+    backups: &[Backup],
+    encryption: Option<&Encryption>,
+) -> ArchiveResult<Vec<u8>> {
+    let mut builder = Builder::new(GzEncoder::new(vec![], Compression::best()));
+    let exported_at = Utc::now();
+
+    let entries: Vec<Value> = backups
+        .iter()
+        .map(|backup| {
+            json!({
+                "file": save_path(backup),
+                "saved_at": backup.saved_at.to_rfc3339(),
+                "label": backup.label,
+                "content_hash": content_hash(&backup.save_code),
+                "cookies_per_second": backup.metrics.and_then(|metrics| metrics.cookies_per_second),
+            })
+        })
+        .collect();
+
+    let manifest = json!({
+        "version": MANIFEST_VERSION,
+        "exported_at": exported_at.to_rfc3339(),
+        "backups": entries,
+    });
+
+    append_file(
+        &mut builder,
+        MANIFEST_PATH,
+        manifest.to_string().as_bytes(),
+        exported_at,
+    )
+    .map_err(ArchiveError::IoError)?;
+
+    for backup in backups {
+        let path = save_path(backup);
+        let save_code = match encryption {
+            Some(encryption) => encryption.encrypt(backup.save_code.as_bytes(), path.as_bytes()),
+            None => backup.save_code.as_bytes().to_vec(),
+        };
+
+        append_file(&mut builder, &path, &save_code, backup.saved_at)
+            .map_err(ArchiveError::IoError)?;
+    }
+
+    builder
+        .into_inner()
+        .and_then(GzEncoder::finish)
+        .map_err(ArchiveError::IoError)
+}
+
+/// Read the backups of an archive created by `export_archive` with the same keys, oldest first
+pub fn import_archive(
+    archive: &[u8],
+    encryption: Option<&Encryption>,
+) -> ArchiveResult<Vec<Backup>> {
+    let mut files = HashMap::new();
+
+    for entry in Archive::new(GzDecoder::new(archive))
+        .entries()
+        .map_err(ArchiveError::IoError)?
+    {
+        let mut entry = entry.map_err(ArchiveError::IoError)?;
+        let path = entry
+            .path()
+            .map_err(ArchiveError::IoError)?
+            .to_string_lossy()
+            .into_owned();
+
+        let mut contents = vec![];
+        entry
+            .read_to_end(&mut contents)
+            .map_err(ArchiveError::IoError)?;
+
+        files.insert(path, contents);
+    }
+
+    let manifest = files
+        .get(MANIFEST_PATH)
+        .ok_or(ArchiveError::MissingManifest)?;
+    let manifest: Value = serde_json::from_slice(manifest)
+        .map_err(|error| ArchiveError::InvalidManifest(error.to_string()))?;
+
+    if manifest["version"].as_u64() != Some(MANIFEST_VERSION) {
+        return Err(ArchiveError::InvalidManifest(format!(
+            "unsupported version {}",
+            manifest["version"]
+        )));
+    }
+
+    let entries = manifest["backups"]
+        .as_array()
+        .ok_or_else(|| ArchiveError::InvalidManifest("missing backups".to_string()))?;
+
+    let mut backups = entries
+        .iter()
+        .map(|entry| {
+            let invalid_entry =
+                || ArchiveError::InvalidManifest(format!("invalid entry {}", entry));
+
+            let path = entry["file"].as_str().ok_or_else(invalid_entry)?;
+            let saved_at = entry["saved_at"]
+                .as_str()
+                .and_then(|saved_at| DateTime::parse_from_rfc3339(saved_at).ok())
+                .ok_or_else(invalid_entry)?;
+
+            let save_code = files
+                .get(path)
+                .ok_or_else(|| ArchiveError::MissingSave(path.to_string()))?;
+            let save_code = encryption::decrypt(encryption, save_code, path.as_bytes())
+                .map_err(|error| ArchiveError::DecryptionFailed(path.to_string(), error))?;
+            let save_code = String::from_utf8(save_code)
+                .map_err(|_| ArchiveError::CorruptedSave(path.to_string()))?;
+
+            if entry["content_hash"].as_str() != Some(&content_hash(&save_code)) {
+                return Err(ArchiveError::CorruptedSave(path.to_string()));
+            }
+
+            Ok(Backup::restore(
+                save_code,
+                saved_at.with_timezone(&Utc),
+                entry["label"].as_str().map(str::to_string),
+                entry["cookies_per_second"].as_f64(),
+            ))
+        })
+        .collect::<ArchiveResult<Vec<_>>>()?;
+
+    backups.sort_by_key(|backup| backup.saved_at);
+
+    Ok(backups)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::super::{backup::Backups, retention::RetentionPolicy, store::MemoryStore};
+    use super::*;
+
+    fn backups() -> Backups {
+        Backups::with_store(
+            Box::new(MemoryStore::default()),
+            RetentionPolicy::parse("1d:7d").unwrap(),
+        )
+    }
+
+    fn backup(save_code: &str, age: Duration) -> Backup {
+        Backup::restore(save_code.to_string(), Utc::now() - age, None, None)
+    }
+
+    #[test]
+    fn export_and_merge() {
+        let mut source = backups();
+        source.add(backup("first", Duration::days(3))).unwrap();
+        source.add(backup("second", Duration::days(2))).unwrap();
+        source.add(backup("third", Duration::days(1))).unwrap();
+
+        let first_id = source.list(2, 1).unwrap()[0].id.unwrap();
+        source.pin(first_id, "start").unwrap();
+
+        let archive = export_archive(&source.since(None).unwrap(), None).unwrap();
+        let imported = import_archive(&archive, None).unwrap();
+        assert_eq!(imported.len(), 3);
+        assert_eq!(imported[0].label.as_deref(), Some("start"));
+
+        // Only the backups missing from the destination are merged
+        let mut destination = backups();
+        destination.add(backup("latest", Duration::zero())).unwrap();
+        let older = source
+            .between(None, Some(Utc::now() - Duration::hours(36)))
+            .unwrap();
+        assert_eq!(destination.merge(older).unwrap(), (2, 0));
+
+        assert_eq!(destination.merge(imported.clone()).unwrap(), (1, 0));
+        assert_eq!(destination.merge(imported).unwrap(), (0, 0));
+
+        // Backups older than the retention policy are not kept
+        let ancient = vec![backup("ancient", Duration::days(30))];
+        assert_eq!(destination.merge(ancient).unwrap(), (1, 1));
+
+        let merged: Vec<String> = destination
+            .take_merged()
+            .unwrap()
+            .into_iter()
+            .map(|backup| backup.save_code)
+            .collect();
+        assert_eq!(merged, ["first", "second", "third"]);
+        assert!(destination.take_merged().unwrap().is_empty());
+
+        let save_codes: Vec<String> = destination
+            .since(None)
+            .unwrap()
+            .into_iter()
+            .map(|backup| backup.save_code)
+            .collect();
+        assert_eq!(save_codes, ["first", "second", "third", "latest"]);
+        assert_eq!(
+            destination.latest_backup().unwrap().unwrap().save_code,
+            "latest"
+        );
+        assert_eq!(
+            destination
+                .get_by_label("start")
+                .unwrap()
+                .unwrap()
+                .save_code,
+            "first"
+        );
+    }
+
+    #[test]
+    fn reject_corrupted_archives() {
+        assert!(matches!(
+            import_archive(b"not an archive", None),
+            Err(ArchiveError::IoError(_))
+        ));
+
+        let archive = export_archive(&[backup("save", Duration::zero())], None).unwrap();
+        let mut files = HashMap::new();
+
+        for entry in Archive::new(GzDecoder::new(&archive[..]))
+            .entries()
+            .unwrap()
+        {
+            let mut entry = entry.unwrap();
+            let path = entry.path().unwrap().to_string_lossy().into_owned();
+            let mut contents = vec![];
+            entry.read_to_end(&mut contents).unwrap();
+            files.insert(path, contents);
+        }
+
+        // Rebuild the archive with a tampered save
+        let mut builder = Builder::new(GzEncoder::new(vec![], Compression::best()));
+        for (path, contents) in &files {
+            let contents = if path == MANIFEST_PATH {
+                contents.clone()
+            } else {
+                b"tampered".to_vec()
+            };
+
+            append_file(&mut builder, path, &contents, Utc::now()).unwrap();
+        }
+        let tampered = builder.into_inner().unwrap().finish().unwrap();
+
+        assert!(matches!(
+            import_archive(&tampered, None),
+            Err(ArchiveError::CorruptedSave(_))
+        ));
+    }
+
+    #[test]
+    fn encrypt_archives() {
+        let encryption = Encryption::new(&[1; 32], &[]).unwrap();
+        let backups = [backup("save", Duration::zero())];
+
+        let archive = export_archive(&backups, Some(&encryption)).unwrap();
+        let mut saves = Archive::new(GzDecoder::new(&archive[..]));
+        for entry in saves.entries().unwrap() {
+            let mut contents = vec![];
+            entry.unwrap().read_to_end(&mut contents).unwrap();
+            assert_ne!(contents, b"save");
+        }
+
+        let imported = import_archive(&archive, Some(&encryption)).unwrap();
+        assert_eq!(imported[0].save_code, "save");
+
+        assert!(matches!(
+            import_archive(&archive, None),
+            Err(ArchiveError::DecryptionFailed(
+                _,
+                DecryptionError::MissingKey
+            ))
+        ));
+
+        // Plain archives cannot be slipped in once backups are encrypted
+        let archive = export_archive(&backups, None).unwrap();
+        assert!(matches!(
+            import_archive(&archive, Some(&encryption)),
+            Err(ArchiveError::DecryptionFailed(
+                _,
+                DecryptionError::Unencrypted
+            ))
+        ));
+    }
+}
@@ -0,0 +1,252 @@
+use std::{env, fmt, str::FromStr};
+
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+
+use super::settings::env_switch;
+
+/// Lucky! pays 15% of the bank, up to 15 minutes of production, so the best
+/// payout needs 100 minutes of production in bank
+const LUCKY_RESERVE_SECONDS: f64 = 6000.0;
+/// Frenzy multiplies production by 7 for the Lucky! effect
+const LUCKY_FRENZY_RESERVE_SECONDS: f64 = LUCKY_RESERVE_SECONDS * 7.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ItemKind {
+    Building,
+    Upgrade,
+}
+
+impl ItemKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Building => "building",
+            Self::Upgrade => "upgrade",
+        }
+    }
+}
+
+impl FromStr for ItemKind {
+    type Err = ();
+
+    fn from_str(kind: &str) -> Result<Self, Self::Err> {
+        match kind {
+            "building" => Ok(Self::Building),
+            "upgrade" => Ok(Self::Upgrade),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Building or upgrade that can be bought
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+    pub kind: ItemKind,
+    pub id: i64,
+    pub name: String,
+    pub price: f64,
+    /// Cookies per second gained by buying it
+    pub cps_gain: f64,
+}
+
+impl Candidate {
+    pub fn from_json(candidate: &Value) -> Option<Self> {
+        Some(Self {
+            kind: candidate["kind"].as_str()?.parse().ok()?,
+            id: candidate["id"].as_i64()?,
+            name: candidate["name"].as_str()?.to_string(),
+            price: candidate["price"].as_f64()?,
+            cps_gain: candidate["cps_gain"].as_f64()?,
+        })
+    }
+
+    /// Seconds until buying it pays for itself, including the time needed to afford it
+    pub fn payback_seconds(&self, available: f64, cps: f64) -> f64 {
+        if self.cps_gain <= 0.0 {
+            return f64::INFINITY;
+        }
+
+        let missing = (self.price - available).max(0.0);
+        let waiting = if missing > 0.0 { missing / cps } else { 0.0 };
+
+        waiting + self.price / self.cps_gain
+    }
+}
+
+/// Purchase made by the auto-buy engine
+#[derive(Debug, Clone, PartialEq)]
+pub struct Purchase {
+    pub kind: ItemKind,
+    pub name: String,
+    pub price: f64,
+    pub cps_gain: f64,
+    pub bought_at: DateTime<Utc>,
+}
+
+/// Cookies kept in bank instead of being spent
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Reserve {
+    None,
+    /// Enough for the best Lucky! payout
+    Lucky,
+    /// Enough for the best Lucky! payout during a Frenzy
+    LuckyFrenzy,
+    /// A number of seconds of production
+    Seconds(f64),
+}
+
+impl Reserve {
+    /// Cookies to keep given the current production
+    pub fn cookies(&self, cps: f64) -> f64 {
+        match self {
+            Self::None => 0.0,
+            Self::Lucky => cps * LUCKY_RESERVE_SECONDS,
+            Self::LuckyFrenzy => cps * LUCKY_FRENZY_RESERVE_SECONDS,
+            Self::Seconds(seconds) => cps * seconds,
+        }
+    }
+}
+
+impl FromStr for Reserve {
+    type Err = ();
+
+    fn from_str(reserve: &str) -> Result<Self, Self::Err> {
+        match reserve {
+            "none" => Ok(Self::None),
+            "lucky" => Ok(Self::Lucky),
+            "lucky_frenzy" => Ok(Self::LuckyFrenzy),
+            seconds => seconds
+                .parse()
+                .ok()
+                .filter(|seconds: &f64| seconds.is_finite() && *seconds >= 0.0)
+                .map(Self::Seconds)
+                .ok_or(()),
+        }
+    }
+}
+
+impl fmt::Display for Reserve {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::None => write!(f, "none"),
+            Self::Lucky => write!(f, "lucky"),
+            Self::LuckyFrenzy => write!(f, "lucky_frenzy"),
+            Self::Seconds(seconds) => write!(f, "{} seconds of production", seconds),
+        }
+    }
+}
+
+/// Strategy of the auto-buy engine
+#[derive(Debug, Clone, PartialEq)]
+pub struct AutoBuy {
+    pub enabled: bool,
+    pub reserve: Reserve,
+}
+
+impl AutoBuy {
+    /// Load the strategy from env `AUTO_BUY` (`on` or `off`) and `AUTO_BUY_RESERVE`
+    pub fn from_env() -> Self {
+        let enabled = env_switch("AUTO_BUY", false);
+
+        let reserve = match env::var("AUTO_BUY_RESERVE") {
+            Ok(reserve) => reserve.parse().expect("Invalid env AUTO_BUY_RESERVE"),
+            Err(_) => Reserve::Lucky,
+        };
+
+        Self { enabled, reserve }
+    }
+
+    /// Pick the candidate with the shortest payback period, if it can be bought without eating the reserve
+    ///
+    /// Cheaper candidates are not bought in the meantime, as they would only
+    /// delay the best one.
+    pub fn choose<'a>(
+        &self,
+        candidates: &'a [Candidate],
+        cookies: f64,
+        cps: f64,
+    ) -> Option<&'a Candidate> {
+        let available = cookies - self.reserve.cookies(cps);
+
+        let best = candidates
+            .iter()
+            .filter(|candidate| candidate.cps_gain > 0.0)
+            .min_by(|a, b| {
+                a.payback_seconds(available, cps)
+                    .total_cmp(&b.payback_seconds(available, cps))
+            })?;
+
+        (best.price <= available).then_some(best)
+    }
+}
+
+impl fmt::Display for AutoBuy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}, reserve: {}",
+            if self.enabled { "running" } else { "paused" },
+            self.reserve
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(name: &str, price: f64, cps_gain: f64) -> Candidate {
+        Candidate {
+            kind: ItemKind::Building,
+            id: 0,
+            name: name.to_string(),
+            price,
+            cps_gain,
+        }
+    }
+
+    #[test]
+    fn choose_best_payback() {
+        let auto_buy = AutoBuy {
+            enabled: true,
+            reserve: Reserve::None,
+        };
+        let candidates = [
+            candidate("Cursor", 15.0, 0.1),
+            candidate("Grandma", 100.0, 1.0),
+            candidate("Kitten", 50.0, 0.0),
+        ];
+
+        // Grandmas pay back in 100 seconds, cursors in 150
+        assert_eq!(
+            auto_buy.choose(&candidates, 1000.0, 10.0).unwrap().name,
+            "Grandma"
+        );
+
+        // Waiting for the best candidate beats buying a worse one now
+        assert_eq!(auto_buy.choose(&candidates, 50.0, 10.0), None);
+
+        // Unless it takes so long to afford that the cheaper one wins
+        assert_eq!(
+            auto_buy.choose(&candidates, 50.0, 0.1).unwrap().name,
+            "Cursor"
+        );
+    }
+
+    #[test]
+    fn keep_reserve() {
+        let auto_buy = AutoBuy {
+            enabled: true,
+            reserve: Reserve::Seconds(60.0),
+        };
+        let candidates = [candidate("Grandma", 100.0, 1.0)];
+
+        assert_eq!(auto_buy.choose(&candidates, 650.0, 10.0), None);
+        assert!(auto_buy.choose(&candidates, 700.0, 10.0).is_some());
+
+        assert_eq!("lucky".parse(), Ok(Reserve::Lucky));
+        assert_eq!("90".parse(), Ok(Reserve::Seconds(90.0)));
+        assert_eq!("-1".parse::<Reserve>(), Err(()));
+        assert_eq!(Reserve::Lucky.cookies(1.0), 6000.0);
+    }
+}
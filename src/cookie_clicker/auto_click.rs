@@ -0,0 +1,142 @@
+use std::{env, fmt};
+
+/// The game ignores clicks less than 20 ms apart, timers need some slack on top of it
+pub const MAX_CLICKS_PER_SECOND: u32 = 40;
+const DEFAULT_CLICKS_PER_SECOND: u32 = 10;
+
+/// Settings and counter of the auto-click task
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AutoClick {
+    pub enabled: bool,
+    clicks_per_second: u32,
+    /// Clicks registered by the game since the browser session started
+    pub clicks: u64,
+    /// Last reading of `Game.cookieClicks`
+    last_cookie_clicks: Option<i64>,
+}
+
+impl AutoClick {
+    /// Load the click rate from env `AUTO_CLICKS_PER_SECOND`, see `parse`
+    pub fn from_env() -> Self {
+        match env::var("AUTO_CLICKS_PER_SECOND") {
+            Ok(clicks_per_second) => {
+                Self::parse(&clicks_per_second).expect("Invalid env AUTO_CLICKS_PER_SECOND")
+            }
+            Err(_) => Self::new(DEFAULT_CLICKS_PER_SECOND),
+        }
+    }
+
+    /// Parse a click rate up to `MAX_CLICKS_PER_SECOND`, `0` pauses auto-click on start
+    fn parse(clicks_per_second: &str) -> Option<Self> {
+        clicks_per_second
+            .parse()
+            .ok()
+            .filter(|clicks_per_second| *clicks_per_second <= MAX_CLICKS_PER_SECOND)
+            .map(Self::new)
+    }
+
+    fn new(clicks_per_second: u32) -> Self {
+        Self {
+            enabled: clicks_per_second > 0,
+            clicks_per_second: if clicks_per_second > 0 {
+                clicks_per_second
+            } else {
+                DEFAULT_CLICKS_PER_SECOND
+            },
+            clicks: 0,
+            last_cookie_clicks: None,
+        }
+    }
+
+    pub fn clicks_per_second(&self) -> u32 {
+        self.clicks_per_second
+    }
+
+    /// Change the click rate, returns `false` when it is out of range
+    pub fn set_clicks_per_second(&mut self, clicks_per_second: u32) -> bool {
+        if !(1..=MAX_CLICKS_PER_SECOND).contains(&clicks_per_second) {
+            return false;
+        }
+
+        self.clicks_per_second = clicks_per_second;
+
+        true
+    }
+
+    /// Count the clicks registered since the previous reading of `Game.cookieClicks`
+    ///
+    /// Clicks dropped by the game are not counted, and neither are the ones
+    /// lost when the counter goes back to zero on ascension.
+    pub fn count_clicks(&mut self, cookie_clicks: i64) {
+        if let Some(last_cookie_clicks) = self.last_cookie_clicks {
+            self.clicks += (cookie_clicks - last_cookie_clicks).max(0) as u64;
+        }
+
+        self.last_cookie_clicks = Some(cookie_clicks);
+    }
+
+    /// Start counting again, for a new browser session
+    pub fn reset_clicks(&mut self) {
+        self.clicks = 0;
+        self.last_cookie_clicks = None;
+    }
+}
+
+impl fmt::Display for AutoClick {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at {} clicks per second, {} clicks performed",
+            if self.enabled { "running" } else { "paused" },
+            self.clicks_per_second,
+            self.clicks
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_click_rates() {
+        let auto_click = AutoClick::parse("10").unwrap();
+        assert!(auto_click.enabled);
+        assert_eq!(auto_click.clicks_per_second(), 10);
+
+        let auto_click = AutoClick::parse("0").unwrap();
+        assert!(!auto_click.enabled);
+        assert_eq!(auto_click.clicks_per_second(), DEFAULT_CLICKS_PER_SECOND);
+
+        assert_eq!(AutoClick::parse("40").unwrap().clicks_per_second(), 40);
+
+        for invalid in ["41", "-1", "fast", ""] {
+            assert_eq!(AutoClick::parse(invalid), None, "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn change_rate_and_count_clicks() {
+        let mut auto_click = AutoClick::new(10);
+
+        assert!(!auto_click.set_clicks_per_second(0));
+        assert!(!auto_click.set_clicks_per_second(MAX_CLICKS_PER_SECOND + 1));
+        assert_eq!(auto_click.clicks_per_second(), 10);
+        assert!(auto_click.set_clicks_per_second(MAX_CLICKS_PER_SECOND));
+        assert_eq!(auto_click.clicks_per_second(), MAX_CLICKS_PER_SECOND);
+
+        // The first reading is only a starting point
+        auto_click.count_clicks(100);
+        auto_click.count_clicks(138);
+        assert_eq!(auto_click.clicks, 38);
+
+        // Ascending starts the game counter again
+        auto_click.count_clicks(5);
+        auto_click.count_clicks(25);
+        assert_eq!(auto_click.clicks, 58);
+
+        auto_click.reset_clicks();
+        auto_click.count_clicks(30);
+        assert_eq!(auto_click.clicks, 0);
+    }
+}
@@ -1,92 +1,359 @@
-use std::{env, path::PathBuf};
+use std::{env, io};
 
 use chrono::{DateTime, Utc};
 use chrono_tz::Tz;
-use rusqlite::{params, Connection, OptionalExtension};
 
-const MAX_BACKUPS_LENGTH: usize = 512;
+use super::{
+    compression::content_hash,
+    encryption::DecryptionError,
+    retention::RetentionPolicy,
+    save::{SaveData, SaveResult},
+    store::{self, BackupStore},
+};
 
 #[derive(Debug)]
 pub enum BackupError {
     RusqliteError(rusqlite::Error),
+    IoError(io::Error),
+    LabelAlreadyUsed(String),
+    /// A stored save code cannot be decrypted with the configured keys
+    DecryptionFailed(DecryptionError),
+    /// No encryption key is configured, or the store does not support encryption
+    EncryptionDisabled,
 }
 
 pub type BackupResult<T> = Result<T, BackupError>;
 
-#[derive(Debug)]
+const SINCE_PAGE_SIZE: usize = 100;
+
+#[derive(Debug, Clone)]
 pub struct Backup {
-    saved_at: DateTime<Utc>,
+    /// Store id, `None` until the backup is stored
+    pub id: Option<i64>,
+    pub(super) saved_at: DateTime<Utc>,
     pub save_code: String,
+    /// Game metrics at backup time, `None` if the save could not be decoded
+    pub metrics: Option<BackupMetrics>,
+    /// Pinned backups have a label and are never pruned
+    pub label: Option<String>,
+}
+
+/// Game metrics stored alongside each backup, so that history doesn't need decoding
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BackupMetrics {
+    /// Cookies in bank
+    pub cookies: f64,
+    /// Cookies baked across all ascensions
+    pub cookies_baked: f64,
+    /// Only known for backups taken from a running game
+    pub cookies_per_second: Option<f64>,
+    pub prestige: f64,
+    pub buildings: i64,
+    pub heavenly_chips: f64,
+}
+
+impl BackupMetrics {
+    pub fn from_save(save: &SaveData) -> Self {
+        Self {
+            cookies: save.stats.cookies,
+            cookies_baked: save.stats.cookies_earned + save.stats.cookies_reset,
+            cookies_per_second: None,
+            prestige: save.stats.prestige,
+            buildings: save.buildings.iter().map(|building| building.amount).sum(),
+            heavenly_chips: save.stats.heavenly_chips,
+        }
+    }
+}
+
+/// Get the timezone used to display dates from env
+pub fn timezone() -> Tz {
+    env::var("TIMEZONE")
+        .expect("Missing env TIMEZONE")
+        .parse()
+        .expect("Invalid env TIMEZONE")
 }
 
 impl Backup {
     /// Create new `CookieClickerBackup` instance
     pub fn new(save_code: String) -> Self {
+        let metrics = SaveData::decode(&save_code)
+            .ok()
+            .map(|save| BackupMetrics::from_save(&save));
+
         Self {
+            id: None,
             saved_at: Utc::now(),
             save_code,
+            metrics,
+            label: None,
         }
     }
 
+    /// Rebuild a backup exported elsewhere, metrics other than cookies per second are decoded again
+    pub(super) fn restore(
+        save_code: String,
+        saved_at: DateTime<Utc>,
+        label: Option<String>,
+        cookies_per_second: Option<f64>,
+    ) -> Self {
+        let mut backup = Self::new(save_code);
+        backup.saved_at = saved_at;
+        backup.label = label;
+
+        match cookies_per_second {
+            Some(cookies_per_second) => backup.with_cookies_per_second(cookies_per_second),
+            None => backup,
+        }
+    }
+
+    /// Record the cookies per second of the running game, which saves don't contain
+    pub fn with_cookies_per_second(mut self, cookies_per_second: f64) -> Self {
+        if let Some(metrics) = self.metrics.as_mut() {
+            metrics.cookies_per_second = Some(cookies_per_second);
+        }
+
+        self
+    }
+
     pub fn saved_at(&self) -> String {
-        let timezone: Tz = env::var("TIMEZONE")
-            .expect("Missing env TIMEZONE")
-            .parse()
-            .expect("Invalid env TIMEZONE");
+        format!("{:?}", self.saved_at_local())
+    }
+
+    pub fn saved_at_utc(&self) -> DateTime<Utc> {
+        self.saved_at
+    }
 
-        let saved_at = self.saved_at.with_timezone(&timezone);
-        format!("{:?}", saved_at)
+    /// Backup date in the configured timezone
+    pub fn saved_at_local(&self) -> DateTime<Tz> {
+        self.saved_at.with_timezone(&timezone())
+    }
+
+    /// Decode the stored save code without starting a browser session
+    pub fn save_data(&self) -> SaveResult<SaveData> {
+        SaveData::decode(&self.save_code)
     }
 }
 
 #[derive(Debug)]
 pub struct Backups {
-    connection: Connection,
+    store: Box<dyn BackupStore>,
+    pub retention: RetentionPolicy,
+    /// Ids of the backups merged since the last call to `take_merged`
+    merged: Vec<i64>,
 }
 
 impl Backups {
+    /// Open the backup store configured in env
     pub fn new() -> BackupResult<Self> {
-        let data_path = env::var("PERSISTENT_DATA_PATH").expect("Missing env PERSISTENT_DATA_PATH");
-        let mut data_path = PathBuf::from(data_path);
-        data_path.push("saves.db");
+        Ok(Self::with_store(
+            store::from_env()?,
+            RetentionPolicy::from_env(),
+        ))
+    }
+
+    pub fn with_store(store: Box<dyn BackupStore>, retention: RetentionPolicy) -> Self {
+        Self {
+            store,
+            retention,
+            merged: vec![],
+        }
+    }
+
+    /// Store a backup, unless it is identical to the latest one. Returns whether it was stored
+    pub fn add(&mut self, backup: Backup) -> BackupResult<bool> {
+        Ok(self.insert(&backup)?.is_some())
+    }
 
-        let mut backups = Self {
-            connection: Connection::open(data_path).map_err(BackupError::RusqliteError)?,
+    /// Store a backup taken elsewhere, keeping its date and label. Returns whether it was stored
+    pub fn import(&mut self, backup: Backup) -> BackupResult<bool> {
+        let id = match self.insert(&backup)? {
+            Some(id) => id,
+            None => return Ok(false),
         };
-        backups.create_tables()?;
 
-        Ok(backups)
+        if let Some(label) = &backup.label {
+            self.pin(id, label)?;
+        }
+
+        Ok(true)
     }
 
-    fn create_tables(&mut self) -> BackupResult<()> {
-        self.connection
-            .execute(include_str!("./sql/schema.sql"), [])
-            .map_err(BackupError::RusqliteError)?;
+    /// Store backups taken elsewhere, skipping the ones already stored
+    ///
+    /// Labels are kept unless another backup already uses them. Merged backups
+    /// are pruned right away like the others, returns how many were stored and
+    /// how many of them were already expired.
+    pub fn merge(&mut self, backups: Vec<Backup>) -> BackupResult<(usize, usize)> {
+        let mut merged = vec![];
 
-        Ok(())
+        for backup in backups {
+            if self.contains(&backup)? {
+                continue;
+            }
+
+            let id = self
+                .store
+                .insert(&backup, &content_hash(&backup.save_code))?;
+
+            if let Some(label) = &backup.label {
+                if self.get_by_label(label)?.is_none() {
+                    self.store.set_label(id, Some(label))?;
+                }
+            }
+
+            merged.push(id);
+        }
+
+        let expired = self.delete_expired()?;
+        let pruned = merged.iter().filter(|id| expired.contains(id)).count();
+        let total = merged.len();
+
+        merged.retain(|id| !expired.contains(id));
+        self.merged.append(&mut merged);
+
+        Ok((total, pruned))
     }
 
-    pub fn add(&mut self, backup: Backup) -> BackupResult<()> {
-        self.connection
-            .execute(
-                include_str!("./sql/insert_backup.sql"),
-                params![backup.save_code, backup.saved_at],
-            )
-            .map_err(BackupError::RusqliteError)?;
+    /// Take the backups merged since the previous call, that are still stored
+    ///
+    /// Merged backups keep their original date, so they are missed by `since`
+    /// once newer backups were replicated.
+    pub fn take_merged(&mut self) -> BackupResult<Vec<Backup>> {
+        let mut backups = vec![];
+
+        for id in std::mem::take(&mut self.merged) {
+            if let Some(backup) = self.get(id)? {
+                backups.push(backup);
+            }
+        }
 
-        Ok(())
+        Ok(backups)
+    }
+
+    /// Whether a backup with the same date and save code is already stored
+    fn contains(&mut self, backup: &Backup) -> BackupResult<bool> {
+        Ok(self
+            .store
+            .latest_before(backup.saved_at)?
+            .is_some_and(|stored| {
+                stored.saved_at == backup.saved_at && stored.save_code == backup.save_code
+            }))
+    }
+
+    /// Insert a backup unless it is identical to the latest one, returns its id
+    fn insert(&mut self, backup: &Backup) -> BackupResult<Option<i64>> {
+        let content_hash = content_hash(&backup.save_code);
+
+        if self.store.latest_content_hash()?.as_ref() == Some(&content_hash) {
+            return Ok(None);
+        }
+
+        self.store.insert(backup, &content_hash).map(Some)
     }
 
     pub fn latest_backup(&mut self) -> BackupResult<Option<Backup>> {
-        self
-            .connection
-            .query_row(include_str!("./sql/get_latest_backup.sql"), [], |row| {
-                Ok(Backup {
-                    save_code: row.get(0)?,
-                    saved_at: row.get(1)?,
-                })
-            })
-            .optional()
-            .map_err(BackupError::RusqliteError)
+        self.store.latest()
+    }
+
+    pub fn get(&mut self, id: i64) -> BackupResult<Option<Backup>> {
+        self.store.get(id)
+    }
+
+    /// Get the most recent backup taken at or before `instant`
+    pub fn latest_before(&mut self, instant: DateTime<Utc>) -> BackupResult<Option<Backup>> {
+        self.store.latest_before(instant)
+    }
+
+    /// List backups newest first, `page` starts from 0
+    pub fn list(&mut self, page: usize, page_size: usize) -> BackupResult<Vec<Backup>> {
+        self.store.list(page * page_size, page_size)
+    }
+
+    /// List the backups taken after `instant`, or all of them, oldest first
+    pub fn since(&mut self, instant: Option<DateTime<Utc>>) -> BackupResult<Vec<Backup>> {
+        let mut backups = vec![];
+
+        for page in 0.. {
+            let page = self.list(page, SINCE_PAGE_SIZE)?;
+            let last_page = page.len() < SINCE_PAGE_SIZE;
+
+            for backup in page {
+                if instant.is_some_and(|instant| backup.saved_at <= instant) {
+                    backups.reverse();
+                    return Ok(backups);
+                }
+
+                backups.push(backup);
+            }
+
+            if last_page {
+                break;
+            }
+        }
+
+        backups.reverse();
+
+        Ok(backups)
+    }
+
+    /// List the backups taken between `from` and `to` included, oldest first
+    pub fn between(
+        &mut self,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> BackupResult<Vec<Backup>> {
+        self.store.between(from, to)
+    }
+
+    pub fn count(&mut self) -> BackupResult<usize> {
+        self.store.count()
+    }
+
+    pub fn get_by_label(&mut self, label: &str) -> BackupResult<Option<Backup>> {
+        self.store.get_by_label(label)
+    }
+
+    /// List pinned backups newest first
+    pub fn pinned(&mut self) -> BackupResult<Vec<Backup>> {
+        self.store.pinned()
+    }
+
+    /// Pin a backup with a label, replacing its previous label. Returns whether the backup exists
+    pub fn pin(&mut self, id: i64, label: &str) -> BackupResult<bool> {
+        if let Some(backup) = self.get_by_label(label)? {
+            if backup.id != Some(id) {
+                return Err(BackupError::LabelAlreadyUsed(label.to_string()));
+            }
+        }
+
+        self.store.set_label(id, Some(label))
+    }
+
+    /// Remove the label of a pinned backup, so it can be pruned again. Returns whether it existed
+    pub fn unpin(&mut self, label: &str) -> BackupResult<bool> {
+        match self.get_by_label(label)?.and_then(|backup| backup.id) {
+            Some(id) => self.store.set_label(id, None),
+            None => Ok(false),
+        }
+    }
+
+    /// Delete the unpinned backups expired according to the retention policy, returns how many were deleted
+    pub fn prune(&mut self) -> BackupResult<usize> {
+        Ok(self.delete_expired()?.len())
+    }
+
+    /// Delete the unpinned backups expired according to the retention policy, returns their ids
+    fn delete_expired(&mut self) -> BackupResult<Vec<i64>> {
+        let backups = self.store.unpinned_dates()?;
+        let expired = self.retention.expired(&backups, Utc::now());
+
+        self.store.delete(&expired)?;
+
+        Ok(expired)
+    }
+
+    /// Encrypt every backup with the current key, so that old keys can be removed
+    pub fn rotate_key(&mut self) -> BackupResult<usize> {
+        self.store.rotate_key()
     }
 }
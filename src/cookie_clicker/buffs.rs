@@ -0,0 +1,39 @@
+use serde_json::Value;
+
+/// Buff types, as named by `Game.buffs[name].type.name`
+pub const FRENZY: &str = "frenzy";
+/// Shared by every building special
+pub const BUILDING_SPECIAL: &str = "building buff";
+pub const CLICK_FRENZY: &str = "click frenzy";
+pub const DRAGONFLIGHT: &str = "dragonflight";
+
+/// Types of the active buffs
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Buffs {
+    types: Vec<String>,
+}
+
+impl Buffs {
+    /// Parse the buff types returned by the game
+    pub fn from_json(buffs: &Value) -> Option<Self> {
+        Some(Self {
+            types: buffs
+                .as_array()?
+                .iter()
+                .map(|buff| Some(buff.as_str()?.to_string()))
+                .collect::<Option<_>>()?,
+        })
+    }
+
+    pub fn has(&self, buff: &str) -> bool {
+        self.types.iter().any(|active| active == buff)
+    }
+}
+
+impl<'a> FromIterator<&'a str> for Buffs {
+    fn from_iter<I: IntoIterator<Item = &'a str>>(types: I) -> Self {
+        Self {
+            types: types.into_iter().map(str::to_string).collect(),
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use std::io::{self, Read, Write};
+
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+use ring::digest;
+
+use super::save::{unwrap_save_code, wrap_save_code, SaveFormat};
+
+/// Save code stored as is
+const RAW_TAG: u8 = 0;
+/// Exported save code stored as its decoded payload
+const EXPORTED_TAG: u8 = 1;
+/// Escaped save code stored as its decoded payload
+const ESCAPED_TAG: u8 = 2;
+
+/// Compress a save code for storage
+///
+/// Base64 hardly compresses, so the decoded payload is stored instead whenever
+/// the exact save code can be rebuilt from it.
+pub fn compress_save_code(save_code: &str) -> Vec<u8> {
+    let (tag, payload) = match unwrap_save_code(save_code) {
+        Ok((format, payload)) if wrap_save_code(format, &payload) == save_code => {
+            let tag = match format {
+                SaveFormat::Exported => EXPORTED_TAG,
+                SaveFormat::Escaped => ESCAPED_TAG,
+            };
+
+            (tag, payload)
+        }
+        _ => (RAW_TAG, save_code.as_bytes().to_vec()),
+    };
+
+    let mut encoder = ZlibEncoder::new(vec![tag], Compression::best());
+    encoder
+        .write_all(&payload)
+        .expect("Writing to a Vec cannot fail");
+
+    encoder.finish().expect("Writing to a Vec cannot fail")
+}
+
+/// Restore a save code compressed with `compress_save_code`
+pub fn decompress_save_code(compressed: &[u8]) -> io::Result<String> {
+    let (tag, compressed) = compressed
+        .split_first()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "Empty compressed save"))?;
+
+    let mut payload = vec![];
+    ZlibDecoder::new(compressed).read_to_end(&mut payload)?;
+
+    let save_code = match *tag {
+        RAW_TAG => String::from_utf8(payload)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?,
+        EXPORTED_TAG => wrap_save_code(SaveFormat::Exported, &payload),
+        ESCAPED_TAG => wrap_save_code(SaveFormat::Escaped, &payload),
+        tag => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unknown compressed save tag {}", tag),
+            ))
+        }
+    };
+
+    Ok(save_code)
+}
+
+/// Hex encoded SHA-256 of a save code
+pub fn content_hash(save_code: &str) -> String {
+    digest::digest(&digest::SHA256, save_code.as_bytes())
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::save::SaveData;
+    use super::*;
+
+    const EXPORTED: &str = include_str!("./test_saves/v2.022.txt");
+    const ESCAPED: &str = include_str!("./test_saves/v2.052.txt");
+
+    #[test]
+    fn round_trip_each_tag() {
+        let save_codes = [
+            ("not a save code", RAW_TAG),
+            // The trailing newline cannot be rebuilt from the payload
+            (EXPORTED, RAW_TAG),
+            (EXPORTED.trim_end(), EXPORTED_TAG),
+            (ESCAPED.trim_end(), ESCAPED_TAG),
+        ];
+
+        for (save_code, tag) in save_codes {
+            let compressed = compress_save_code(save_code);
+            let decompressed = decompress_save_code(&compressed).unwrap();
+
+            assert_eq!(compressed[0], tag, "{}", save_code);
+            assert_eq!(decompressed, save_code);
+            assert_eq!(content_hash(&decompressed), content_hash(save_code));
+        }
+    }
+
+    #[test]
+    fn reject_unknown_tags() {
+        let mut compressed = compress_save_code("not a save code");
+        compressed[0] = 3;
+
+        assert_eq!(
+            decompress_save_code(&compressed).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+        assert_eq!(
+            decompress_save_code(&[]).unwrap_err().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+    }
+
+    #[test]
+    fn hash_save_codes() {
+        assert_eq!(
+            content_hash(""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+
+        // A save encoded again is still the same backup
+        let save_code = ESCAPED.trim_end();
+        let encoded = SaveData::decode(save_code).unwrap().encode();
+        assert_eq!(content_hash(&encoded), content_hash(save_code));
+        assert_ne!(content_hash(save_code), content_hash(EXPORTED.trim_end()));
+    }
+}
@@ -0,0 +1,142 @@
+use std::fmt;
+
+use super::save::{beautify, building_name, SaveData};
+
+/// What changed between two saves of the same game
+#[derive(Debug, Clone, PartialEq)]
+pub struct SaveDiff {
+    /// Cookies baked across all ascensions
+    pub cookies_baked: f64,
+    /// Change of the cookies in bank
+    pub cookies: f64,
+    pub ascensions: i64,
+    /// Change of the amount of each building, by id, without unchanged buildings
+    pub buildings: Vec<(usize, i64)>,
+    pub upgrades_bought: usize,
+    pub achievements_won: usize,
+    pub lumps_harvested: f64,
+    pub lumps_spent: f64,
+}
+
+impl SaveDiff {
+    pub fn between(before: &SaveData, after: &SaveData) -> Self {
+        let amount = |save: &SaveData, id: usize| {
+            save.buildings
+                .get(id)
+                .map(|building| building.amount)
+                .unwrap_or_default()
+        };
+        let buildings = (0..before.buildings.len().max(after.buildings.len()))
+            .map(|id| (id, amount(after, id) - amount(before, id)))
+            .filter(|(_, change)| *change != 0)
+            .collect();
+
+        let upgrades_bought = after
+            .upgrades
+            .iter()
+            .enumerate()
+            .filter(|(id, upgrade)| {
+                upgrade.bought && !before.upgrades.get(*id).is_some_and(|before| before.bought)
+            })
+            .count();
+        let achievements_won = after
+            .achievements
+            .iter()
+            .enumerate()
+            .filter(|(id, won)| **won && !before.achievements.get(*id).is_some_and(|won| *won))
+            .count();
+
+        // Lumps are `-1` until they are unlocked
+        let lumps = |save: &SaveData| save.stats.lumps.max(0.0);
+        let lumps_harvested = after.stats.lumps_total - before.stats.lumps_total;
+        let lumps_spent = (lumps(before) + lumps_harvested - lumps(after)).max(0.0);
+
+        let cookies_baked = |save: &SaveData| save.stats.cookies_earned + save.stats.cookies_reset;
+
+        Self {
+            cookies_baked: cookies_baked(after) - cookies_baked(before),
+            cookies: after.stats.cookies - before.stats.cookies,
+            ascensions: after.stats.resets - before.stats.resets,
+            buildings,
+            upgrades_bought,
+            achievements_won,
+            lumps_harvested,
+            lumps_spent,
+        }
+    }
+}
+
+impl fmt::Display for SaveDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Cookies baked: {}", beautify(self.cookies_baked))?;
+        writeln!(f, "Cookies in bank: {}", beautify(self.cookies))?;
+        writeln!(f, "Ascensions: {}", self.ascensions)?;
+
+        if self.buildings.is_empty() {
+            writeln!(f, "Buildings: unchanged")?;
+        } else {
+            let buildings: Vec<String> = self
+                .buildings
+                .iter()
+                .map(|(id, change)| format!("{:+} {}", change, building_name(*id)))
+                .collect();
+
+            writeln!(f, "Buildings: {}", buildings.join(", "))?;
+        }
+
+        writeln!(f, "Upgrades bought: {}", self.upgrades_bought)?;
+        writeln!(f, "Achievements won: {}", self.achievements_won)?;
+        write!(
+            f,
+            "Sugar lumps: {} harvested, {} spent",
+            self.lumps_harvested, self.lumps_spent
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAVE: &str = include_str!("./test_saves/v2.052.txt");
+
+    #[test]
+    fn diff_saves() {
+        let mut before = SaveData::decode(SAVE.trim_end()).unwrap();
+        before.stats.cookies = 2000.0;
+        before.stats.cookies_earned = 5000.0;
+        before.stats.cookies_reset = 0.0;
+        let mut after = before.clone();
+
+        assert_eq!(SaveDiff::between(&before, &after).buildings, []);
+
+        after.stats.cookies_earned += 1000.0;
+        after.stats.cookies -= 500.0;
+        after.buildings[2].amount += 3;
+        after.buildings[0].amount -= 1;
+        after.stats.lumps_total += 2.0;
+        after.stats.lumps = before.stats.lumps.max(0.0) + 1.0;
+
+        let new_upgrade = before
+            .upgrades
+            .iter()
+            .position(|upgrade| !upgrade.bought)
+            .unwrap();
+        after.upgrades[new_upgrade].bought = true;
+
+        let new_achievement = before.achievements.iter().position(|won| !won).unwrap();
+        after.achievements[new_achievement] = true;
+
+        let diff = SaveDiff::between(&before, &after);
+        assert_eq!(diff.cookies_baked, 1000.0);
+        assert_eq!(diff.cookies, -500.0);
+        assert_eq!(diff.ascensions, 0);
+        assert_eq!(diff.buildings, [(0, -1), (2, 3)]);
+        assert_eq!(diff.upgrades_bought, 1);
+        assert_eq!(diff.achievements_won, 1);
+        assert_eq!(diff.lumps_harvested, 2.0);
+        assert_eq!(diff.lumps_spent, 1.0);
+
+        assert!(diff.to_string().contains("Buildings: -1 Cursor, +3 Farm"));
+    }
+}
@@ -0,0 +1,266 @@
+use std::{env, error, fmt};
+
+use ring::{
+    aead::{self, Aad, LessSafeKey, Nonce, UnboundKey, NONCE_LEN},
+    digest,
+    rand::{SecureRandom, SystemRandom},
+};
+
+/// First byte of encrypted data, unused by `compress_save_code` tags
+const ENCRYPTED_TAG: u8 = 0x80;
+const KEY_ID_LEN: usize = 4;
+const HEADER_LEN: usize = 1 + KEY_ID_LEN + NONCE_LEN;
+
+#[derive(Debug)]
+pub enum DecryptionError {
+    /// The data is encrypted but no key is configured
+    MissingKey,
+    /// A key is configured but the data is not encrypted
+    Unencrypted,
+    /// The data was encrypted with a key that is not configured anymore
+    UnknownKey,
+    /// The data was tampered with or is truncated
+    InvalidCiphertext,
+}
+
+impl fmt::Display for DecryptionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingKey => write!(f, "the backup is encrypted but no key is configured"),
+            Self::Unencrypted => write!(
+                f,
+                "the backup is not encrypted although a key is configured"
+            ),
+            Self::UnknownKey => write!(f, "the backup is encrypted with an unknown key"),
+            Self::InvalidCiphertext => write!(f, "the encrypted backup is corrupted"),
+        }
+    }
+}
+
+impl error::Error for DecryptionError {}
+
+/// ChaCha20-Poly1305 key along with the id stored in front of the data it encrypts
+#[derive(Debug)]
+struct Key {
+    id: [u8; KEY_ID_LEN],
+    key: LessSafeKey,
+}
+
+impl Key {
+    /// Build a key from 32 raw bytes
+    fn new(key: &[u8]) -> Option<Self> {
+        let mut id = [0; KEY_ID_LEN];
+        id.copy_from_slice(&digest::digest(&digest::SHA256, key).as_ref()[..KEY_ID_LEN]);
+
+        let key = UnboundKey::new(&aead::CHACHA20_POLY1305, key).ok()?;
+
+        Some(Self {
+            id,
+            key: LessSafeKey::new(key),
+        })
+    }
+
+    /// Parse a base64 encoded key
+    fn parse(key: &str) -> Option<Self> {
+        Self::new(&base64::decode(key.trim()).ok()?)
+    }
+}
+
+/// Authenticated encryption of stored save codes
+///
+/// Data is always encrypted with the current key, old keys are only kept to
+/// decrypt data until it is encrypted again with the current key. The
+/// associated data binds a ciphertext to where it is stored, such as the
+/// row of a backup, so it cannot be moved elsewhere.
+#[derive(Debug)]
+pub struct Encryption {
+    key: Key,
+    old_keys: Vec<Key>,
+    random: SystemRandom,
+}
+
+impl Encryption {
+    pub fn new(key: &[u8], old_keys: &[&[u8]]) -> Option<Self> {
+        Some(Self {
+            key: Key::new(key)?,
+            old_keys: old_keys
+                .iter()
+                .map(|key| Key::new(key))
+                .collect::<Option<_>>()?,
+            random: SystemRandom::new(),
+        })
+    }
+
+    /// Load the base64 encoded keys from env `BACKUP_ENCRYPTION_KEY` and `BACKUP_ENCRYPTION_OLD_KEYS`
+    ///
+    /// Returns `None` when encryption is disabled, that is when the key is not set or empty.
+    pub fn from_env() -> Option<Self> {
+        let key = env::var("BACKUP_ENCRYPTION_KEY")
+            .ok()
+            .filter(|key| !key.trim().is_empty())?;
+        let old_keys = env::var("BACKUP_ENCRYPTION_OLD_KEYS").unwrap_or_default();
+
+        Some(Self {
+            key: Key::parse(&key).expect("Invalid env BACKUP_ENCRYPTION_KEY"),
+            old_keys: old_keys
+                .split(',')
+                .filter(|key| !key.trim().is_empty())
+                .map(|key| Key::parse(key).expect("Invalid env BACKUP_ENCRYPTION_OLD_KEYS"))
+                .collect(),
+            random: SystemRandom::new(),
+        })
+    }
+
+    /// Encrypt data with the current key, bound to `aad`
+    pub fn encrypt(&self, data: &[u8], aad: &[u8]) -> Vec<u8> {
+        let mut nonce = [0; NONCE_LEN];
+        self.random
+            .fill(&mut nonce)
+            .expect("Cannot generate a random nonce");
+
+        let mut encrypted = data.to_vec();
+        self.key
+            .key
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(aad),
+                &mut encrypted,
+            )
+            .expect("Data is too large to be encrypted");
+
+        let mut header = vec![ENCRYPTED_TAG];
+        header.extend_from_slice(&self.key.id);
+        header.extend_from_slice(&nonce);
+        header.append(&mut encrypted);
+
+        header
+    }
+
+    /// Whether data is already encrypted with the current key
+    pub fn is_current(&self, data: &[u8]) -> bool {
+        is_encrypted(data) && data[1..1 + KEY_ID_LEN] == self.key.id
+    }
+}
+
+/// Whether data was encrypted by `Encryption::encrypt`, with any key
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.first() == Some(&ENCRYPTED_TAG)
+}
+
+/// Decrypt data encrypted with `Encryption::encrypt` and the same `aad`
+///
+/// Data that is not encrypted is only returned as is when no key is
+/// configured, otherwise it could be swapped for data of an attacker.
+pub fn decrypt(
+    encryption: Option<&Encryption>,
+    data: &[u8],
+    aad: &[u8],
+) -> Result<Vec<u8>, DecryptionError> {
+    let encryption = match encryption {
+        Some(encryption) if is_encrypted(data) => encryption,
+        Some(_) => return Err(DecryptionError::Unencrypted),
+        None if is_encrypted(data) => return Err(DecryptionError::MissingKey),
+        None => return Ok(data.to_vec()),
+    };
+
+    if data.len() < HEADER_LEN {
+        return Err(DecryptionError::InvalidCiphertext);
+    }
+
+    let (header, encrypted) = data.split_at(HEADER_LEN);
+    let key_id = &header[1..1 + KEY_ID_LEN];
+
+    let key = std::iter::once(&encryption.key)
+        .chain(&encryption.old_keys)
+        .find(|key| key.id == key_id)
+        .ok_or(DecryptionError::UnknownKey)?;
+
+    let nonce = Nonce::try_assume_unique_for_key(&header[1 + KEY_ID_LEN..])
+        .map_err(|_| DecryptionError::InvalidCiphertext)?;
+
+    let mut decrypted = encrypted.to_vec();
+    let decrypted_length = key
+        .key
+        .open_in_place(nonce, Aad::from(aad), &mut decrypted)
+        .map_err(|_| DecryptionError::InvalidCiphertext)?
+        .len();
+    decrypted.truncate(decrypted_length);
+
+    Ok(decrypted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OLD_KEY: &[u8] = &[1; 32];
+    const KEY: &[u8] = &[2; 32];
+
+    #[test]
+    fn encrypt_and_decrypt() {
+        let encryption = Encryption::new(KEY, &[]).unwrap();
+
+        let encrypted = encryption.encrypt(b"save code", b"hash");
+        assert!(encryption.is_current(&encrypted));
+        assert_ne!(&encrypted[HEADER_LEN..HEADER_LEN + 9], b"save code");
+        assert_eq!(
+            decrypt(Some(&encryption), &encrypted, b"hash").unwrap(),
+            b"save code"
+        );
+
+        // Plain data is only readable without a key
+        assert_eq!(decrypt(None, b"\x00plain", b"hash").unwrap(), b"\x00plain");
+        assert!(matches!(
+            decrypt(Some(&encryption), b"\x00plain", b"hash"),
+            Err(DecryptionError::Unencrypted)
+        ));
+        assert!(!encryption.is_current(b"\x00plain"));
+    }
+
+    #[test]
+    fn decrypt_with_old_keys() {
+        let old_encryption = Encryption::new(OLD_KEY, &[]).unwrap();
+        let encrypted = old_encryption.encrypt(b"save code", b"hash");
+
+        let encryption = Encryption::new(KEY, &[OLD_KEY]).unwrap();
+        assert!(!encryption.is_current(&encrypted));
+        assert_eq!(
+            decrypt(Some(&encryption), &encrypted, b"hash").unwrap(),
+            b"save code"
+        );
+
+        let encryption = Encryption::new(KEY, &[]).unwrap();
+        assert!(matches!(
+            decrypt(Some(&encryption), &encrypted, b"hash"),
+            Err(DecryptionError::UnknownKey)
+        ));
+        assert!(matches!(
+            decrypt(None, &encrypted, b"hash"),
+            Err(DecryptionError::MissingKey)
+        ));
+    }
+
+    #[test]
+    fn reject_tampered_data() {
+        let encryption = Encryption::new(KEY, &[]).unwrap();
+
+        // Data moved elsewhere is rejected
+        let encrypted = encryption.encrypt(b"save code", b"hash");
+        assert!(matches!(
+            decrypt(Some(&encryption), &encrypted, b"other hash"),
+            Err(DecryptionError::InvalidCiphertext)
+        ));
+
+        let mut encrypted = encryption.encrypt(b"save code", b"hash");
+        *encrypted.last_mut().unwrap() ^= 1;
+
+        assert!(matches!(
+            decrypt(Some(&encryption), &encrypted, b"hash"),
+            Err(DecryptionError::InvalidCiphertext)
+        ));
+        assert!(matches!(
+            decrypt(Some(&encryption), &encrypted[..HEADER_LEN - 1], b"hash"),
+            Err(DecryptionError::InvalidCiphertext)
+        ));
+    }
+}
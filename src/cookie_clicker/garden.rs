@@ -0,0 +1,766 @@
+use std::{collections::HashMap, env, fmt, str::FromStr};
+
+use serde_json::{json, Value};
+
+use super::settings::env_switch;
+
+/// How parents are arranged around the empty tiles where mutations grow
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Arrangement {
+    /// Diagonal stripes, every empty tile touches both parents
+    Diagonal,
+    /// Rows of each parent, every empty tile touches three of each
+    Rows,
+    /// Rings of a single parent, every empty tile is surrounded by eight of them
+    Ring,
+}
+
+/// Seed unlocked by growing its parents next to empty tiles
+struct Recipe {
+    child: &'static str,
+    parents: (&'static str, &'static str),
+    arrangement: Arrangement,
+}
+
+const fn recipe(child: &'static str, first: &'static str, second: &'static str) -> Recipe {
+    Recipe {
+        child,
+        parents: (first, second),
+        arrangement: Arrangement::Diagonal,
+    }
+}
+
+/// Mutations in the order seeds are usually unlocked, by plant key
+///
+/// Meddleweed, Crumbspore and Brown mold don't have parents: meddleweed
+/// grows on its own in empty plots and harvesting it may drop the others.
+const RECIPES: [Recipe; 30] = [
+    recipe("thumbcorn", "bakerWheat", "bakerWheat"),
+    recipe("bakeberry", "bakerWheat", "bakerWheat"),
+    recipe("cronerice", "bakerWheat", "thumbcorn"),
+    recipe("gildmillet", "cronerice", "thumbcorn"),
+    recipe("clover", "bakerWheat", "gildmillet"),
+    recipe("goldenClover", "bakerWheat", "gildmillet"),
+    recipe("shimmerlily", "clover", "gildmillet"),
+    recipe("elderwort", "shimmerlily", "cronerice"),
+    recipe("whiteMildew", "brownMold", "brownMold"),
+    recipe("chocoroot", "bakerWheat", "brownMold"),
+    recipe("whiteChocoroot", "chocoroot", "whiteMildew"),
+    recipe("queenbeet", "chocoroot", "bakeberry"),
+    recipe("duketater", "queenbeet", "queenbeet"),
+    recipe("tidygrass", "bakerWheat", "whiteChocoroot"),
+    recipe("whiskerbloom", "shimmerlily", "whiteChocoroot"),
+    recipe("chimerose", "shimmerlily", "whiskerbloom"),
+    recipe("nursetulip", "whiskerbloom", "whiskerbloom"),
+    recipe("greenRot", "whiteMildew", "clover"),
+    recipe("keenmoss", "greenRot", "brownMold"),
+    recipe("drowsyfern", "chocoroot", "keenmoss"),
+    recipe("wardlichen", "cronerice", "keenmoss"),
+    recipe("doughshroom", "crumbspore", "crumbspore"),
+    recipe("glovemorel", "crumbspore", "thumbcorn"),
+    recipe("cheapcap", "crumbspore", "shimmerlily"),
+    recipe("wrinklegill", "crumbspore", "brownMold"),
+    recipe("foolBolete", "doughshroom", "greenRot"),
+    recipe("ichorpuff", "elderwort", "crumbspore"),
+    recipe("shriekbulb", "elderwort", "elderwort"),
+    Recipe {
+        child: "everdaisy",
+        parents: ("tidygrass", "elderwort"),
+        arrangement: Arrangement::Rows,
+    },
+    Recipe {
+        child: "queenbeetLump",
+        parents: ("queenbeet", "queenbeet"),
+        arrangement: Arrangement::Ring,
+    },
+];
+
+/// Plants dropped by meddleweed, which is kept until they are unlocked
+const MEDDLEWEED_DROPS: [&str; 2] = ["crumbspore", "brownMold"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Soil {
+    pub key: String,
+    pub name: String,
+    /// Soils are unlocked by owning enough farms
+    pub unlocked: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Plant {
+    pub key: String,
+    pub name: String,
+    /// Age at which the plant is mature
+    pub mature: f64,
+    /// Whether its seed is unlocked
+    pub unlocked: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tile {
+    /// Tiles are unlocked by levelling farms up
+    pub unlocked: bool,
+    /// Plant id, `None` when empty
+    pub plant: Option<usize>,
+    pub age: f64,
+}
+
+/// State of the Farm minigame
+#[derive(Debug, Clone, PartialEq)]
+pub struct GardenState {
+    /// Current soil id
+    pub soil: usize,
+    /// Whether the soil can be changed now
+    pub soil_ready: bool,
+    /// Soils, by id
+    pub soils: Vec<Soil>,
+    /// Plants, by id
+    pub plants: Vec<Plant>,
+    /// Tiles, by row
+    pub plot: Vec<Vec<Tile>>,
+}
+
+impl GardenState {
+    /// Parse the state returned by the game
+    pub fn from_json(state: &Value) -> Option<Self> {
+        let soils = state["soils"]
+            .as_array()?
+            .iter()
+            .map(|soil| {
+                Some(Soil {
+                    key: soil["key"].as_str()?.to_string(),
+                    name: soil["name"].as_str()?.to_string(),
+                    unlocked: soil["unlocked"].as_bool()?,
+                })
+            })
+            .collect::<Option<_>>()?;
+
+        let plants = state["plants"]
+            .as_array()?
+            .iter()
+            .map(|plant| {
+                Some(Plant {
+                    key: plant["key"].as_str()?.to_string(),
+                    name: plant["name"].as_str()?.to_string(),
+                    mature: plant["mature"].as_f64()?,
+                    unlocked: plant["unlocked"].as_bool()?,
+                })
+            })
+            .collect::<Option<_>>()?;
+
+        let plot = state["plot"]
+            .as_array()?
+            .iter()
+            .map(|row| {
+                row.as_array()?
+                    .iter()
+                    .map(|tile| {
+                        let plant = tile["plant"].as_i64()?;
+
+                        Some(Tile {
+                            unlocked: tile["unlocked"].as_bool()?,
+                            plant: usize::try_from(plant).ok(),
+                            age: tile["age"].as_f64()?,
+                        })
+                    })
+                    .collect::<Option<_>>()
+            })
+            .collect::<Option<_>>()?;
+
+        Some(Self {
+            soil: state["soil"].as_u64()? as usize,
+            soil_ready: state["soil_ready"].as_bool()?,
+            soils,
+            plants,
+            plot,
+        })
+    }
+
+    /// Draw the unlocked tiles as text, one letter per plant: uppercase once mature, `.` when empty
+    ///
+    /// The plot can hold more plants than there are letters, the ones past `z` are all drawn as `?`.
+    pub fn render(&self) -> String {
+        let mut letters: HashMap<usize, char> = HashMap::new();
+        let mut legend = vec![];
+        let mut others = vec![];
+        let mut rows = vec![];
+
+        for row in &self.plot {
+            let mut line = String::new();
+
+            for tile in row.iter().filter(|tile| tile.unlocked) {
+                let plant = match tile.plant {
+                    Some(plant) => plant,
+                    None => {
+                        line.push('.');
+                        continue;
+                    }
+                };
+
+                let letter = *letters.entry(plant).or_insert_with(|| {
+                    let name = self.plants[plant].name.as_str();
+
+                    if legend.len() >= 26 {
+                        others.push(name);
+                        return '?';
+                    }
+
+                    let letter = (b'a' + legend.len() as u8) as char;
+                    legend.push(format!("{}: {}", letter.to_ascii_uppercase(), name));
+
+                    letter
+                });
+
+                if tile.age >= self.plants[plant].mature {
+                    line.push(letter.to_ascii_uppercase());
+                } else {
+                    line.push(letter);
+                }
+            }
+
+            if !line.is_empty() {
+                rows.push(line);
+            }
+        }
+
+        let soil = self
+            .soils
+            .get(self.soil)
+            .map(|soil| soil.name.as_str())
+            .unwrap_or("unknown");
+
+        if !others.is_empty() {
+            legend.push(format!("?: {}", others.join(", ")));
+        }
+
+        let mut rendered = format!("Soil: {}\n\n{}", soil, rows.join("\n"));
+
+        if !legend.is_empty() {
+            rendered.push_str("\n\n");
+            rendered.push_str(&legend.join("\n"));
+        }
+
+        rendered
+    }
+
+    fn plant_id(&self, key: &str) -> Option<usize> {
+        self.plants.iter().position(|plant| plant.key == key)
+    }
+
+    fn is_unlocked(&self, key: &str) -> bool {
+        self.plant_id(key)
+            .is_some_and(|plant| self.plants[plant].unlocked)
+    }
+
+    /// Top left corner of the unlocked tiles
+    fn origin(&self) -> (usize, usize) {
+        let unlocked = |x: usize, y: usize| self.plot[y][x].unlocked;
+        let coordinates = self
+            .plot
+            .iter()
+            .enumerate()
+            .flat_map(|(y, row)| (0..row.len()).map(move |x| (x, y)));
+
+        coordinates
+            .filter(|(x, y)| unlocked(*x, *y))
+            .fold(
+                None,
+                |origin: Option<(usize, usize)>, (x, y)| match origin {
+                    Some((origin_x, origin_y)) => Some((origin_x.min(x), origin_y.min(y))),
+                    None => Some((x, y)),
+                },
+            )
+            .unwrap_or_default()
+    }
+}
+
+/// Plants to grow on each tile
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Layout {
+    Empty,
+    /// The same plant everywhere
+    Fill(usize),
+    /// Parents arranged for a mutation
+    Mutation(Arrangement, usize, usize),
+}
+
+impl Layout {
+    /// Plant for a tile, relative to the top left corner of the unlocked tiles
+    fn plant_at(&self, x: usize, y: usize) -> Option<usize> {
+        match *self {
+            Self::Empty => None,
+            Self::Fill(plant) => Some(plant),
+            Self::Mutation(Arrangement::Diagonal, first, second) => match (x + y) % 3 {
+                0 => Some(first),
+                1 => None,
+                _ => Some(second),
+            },
+            Self::Mutation(Arrangement::Rows, first, second) => match y % 3 {
+                0 => Some(first),
+                1 => None,
+                _ => Some(second),
+            },
+            Self::Mutation(Arrangement::Ring, plant, _) => {
+                (x % 3 != 1 || y % 3 != 1).then_some(plant)
+            }
+        }
+    }
+}
+
+/// Something to do in the garden
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GardenAction {
+    Plant {
+        x: usize,
+        y: usize,
+        plant: usize,
+    },
+    Harvest {
+        x: usize,
+        y: usize,
+        plant: usize,
+    },
+    SetSoil(usize),
+    /// Sacrifice the garden for sugar lumps, locking every seed but Baker's wheat
+    Sacrifice,
+}
+
+impl GardenAction {
+    /// Action as understood by the script that performs it
+    pub fn to_json(self) -> Value {
+        match self {
+            Self::Plant { x, y, plant } => {
+                json!({ "kind": "plant", "x": x, "y": y, "plant": plant })
+            }
+            Self::Harvest { x, y, .. } => json!({ "kind": "harvest", "x": x, "y": y }),
+            Self::SetSoil(soil) => json!({ "kind": "soil", "soil": soil }),
+            Self::Sacrifice => json!({ "kind": "sacrifice" }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GardenGoal {
+    /// Only watch the garden
+    Idle,
+    /// Grow mutations until every seed is unlocked
+    UnlockSeeds,
+    /// Grow Bakeberries on every tile and harvest them for cookies
+    Bakeberries,
+}
+
+impl FromStr for GardenGoal {
+    type Err = ();
+
+    fn from_str(goal: &str) -> Result<Self, Self::Err> {
+        match goal {
+            "idle" => Ok(Self::Idle),
+            "unlock_seeds" => Ok(Self::UnlockSeeds),
+            "bakeberries" => Ok(Self::Bakeberries),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for GardenGoal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let goal = match self {
+            Self::Idle => "idle",
+            Self::UnlockSeeds => "unlock_seeds",
+            Self::Bakeberries => "bakeberries",
+        };
+
+        write!(f, "{}", goal)
+    }
+}
+
+/// Settings of the garden task
+#[derive(Debug, Clone, PartialEq)]
+pub struct Garden {
+    pub goal: GardenGoal,
+    /// Sacrifice the garden once every seed is unlocked
+    pub sacrifice: bool,
+}
+
+impl Garden {
+    /// Load the settings from env `GARDEN_GOAL` and `GARDEN_SACRIFICE` (`on` or `off`)
+    pub fn from_env() -> Self {
+        let goal = match env::var("GARDEN_GOAL") {
+            Ok(goal) => goal.parse().expect("Invalid env GARDEN_GOAL"),
+            Err(_) => GardenGoal::Idle,
+        };
+
+        let sacrifice = env_switch("GARDEN_SACRIFICE", false);
+
+        Self { goal, sacrifice }
+    }
+
+    fn layout(&self, state: &GardenState) -> Layout {
+        match self.goal {
+            GardenGoal::Idle => Layout::Empty,
+            GardenGoal::Bakeberries => match state.plant_id("bakeberry") {
+                Some(bakeberry) if state.plants[bakeberry].unlocked => Layout::Fill(bakeberry),
+                _ => Layout::Empty,
+            },
+            GardenGoal::UnlockSeeds => {
+                let plant_id = |key| {
+                    state
+                        .plant_id(key)
+                        .filter(|plant| state.plants[*plant].unlocked)
+                };
+
+                RECIPES
+                    .iter()
+                    .filter(|recipe| {
+                        state
+                            .plant_id(recipe.child)
+                            .is_some_and(|child| !state.plants[child].unlocked)
+                    })
+                    .find_map(|recipe| {
+                        Some(Layout::Mutation(
+                            recipe.arrangement,
+                            plant_id(recipe.parents.0)?,
+                            plant_id(recipe.parents.1)?,
+                        ))
+                    })
+                    .unwrap_or(Layout::Empty)
+            }
+        }
+    }
+
+    /// Wood chips boost mutations, fertilizer makes plants grow faster
+    fn soil(&self, state: &GardenState) -> Option<usize> {
+        let preferences: &[&str] = match self.goal {
+            GardenGoal::Idle => return None,
+            GardenGoal::UnlockSeeds => &["woodchips", "fertilizer", "dirt"],
+            GardenGoal::Bakeberries => &["fertilizer", "dirt"],
+        };
+
+        preferences.iter().find_map(|key| {
+            state
+                .soils
+                .iter()
+                .position(|soil| soil.key == *key && soil.unlocked)
+        })
+    }
+
+    /// Decide what to plant, harvest and which soil to use
+    ///
+    /// New seeds are harvested once mature to unlock them, and plants that
+    /// don't belong to the layout are removed. Meddleweed is kept while it may
+    /// still drop locked seeds.
+    pub fn plan(&self, state: &GardenState) -> Vec<GardenAction> {
+        if self.goal == GardenGoal::Idle {
+            return vec![];
+        }
+
+        if self.sacrifice && state.plants.iter().all(|plant| plant.unlocked) {
+            return vec![GardenAction::Sacrifice];
+        }
+
+        let mut actions = vec![];
+
+        if let Some(soil) = self.soil(state) {
+            if state.soil_ready && state.soil != soil {
+                actions.push(GardenAction::SetSoil(soil));
+            }
+        }
+
+        let layout = self.layout(state);
+        let (origin_x, origin_y) = state.origin();
+        let keep_meddleweed = self.goal == GardenGoal::UnlockSeeds
+            && MEDDLEWEED_DROPS.iter().any(|key| !state.is_unlocked(key));
+
+        for (y, row) in state.plot.iter().enumerate() {
+            for (x, tile) in row.iter().enumerate() {
+                if !tile.unlocked {
+                    continue;
+                }
+
+                let wanted =
+                    layout.plant_at(x.saturating_sub(origin_x), y.saturating_sub(origin_y));
+
+                let plant = match tile.plant {
+                    Some(plant) => plant,
+                    None => {
+                        if let Some(plant) = wanted {
+                            actions.push(GardenAction::Plant { x, y, plant });
+                        }
+
+                        continue;
+                    }
+                };
+
+                let info = &state.plants[plant];
+                let mature = tile.age >= info.mature;
+
+                let harvest = if !info.unlocked || (keep_meddleweed && info.key == "meddleweed") {
+                    mature
+                } else if wanted == Some(plant) {
+                    self.goal == GardenGoal::Bakeberries && mature
+                } else {
+                    true
+                };
+
+                if harvest {
+                    actions.push(GardenAction::Harvest { x, y, plant });
+                }
+            }
+        }
+
+        actions
+    }
+}
+
+impl fmt::Display for Garden {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "goal {}, sacrifice {}",
+            self.goal,
+            if self.sacrifice { "on" } else { "off" }
+        )
+    }
+}
+
+/// What the garden task did
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GardenReport {
+    pub planted: usize,
+    pub harvested: usize,
+    /// Names of the seeds unlocked by harvesting new plants
+    pub unlocked: Vec<String>,
+    /// Name of the soil switched to
+    pub soil: Option<String>,
+    pub sacrificed: bool,
+}
+
+impl GardenReport {
+    /// Account for an action the game performed
+    pub fn add(&mut self, action: &GardenAction, state: &GardenState) {
+        match *action {
+            GardenAction::Plant { .. } => self.planted += 1,
+            GardenAction::Harvest { plant, .. } => {
+                self.harvested += 1;
+
+                if !state.plants[plant].unlocked {
+                    self.unlocked.push(state.plants[plant].name.clone());
+                }
+            }
+            GardenAction::SetSoil(soil) => self.soil = Some(state.soils[soil].name.clone()),
+            GardenAction::Sacrifice => self.sacrificed = true,
+        }
+    }
+
+    /// Whether it is worth telling the admin about
+    pub fn is_notable(&self) -> bool {
+        !self.unlocked.is_empty() || self.sacrificed
+    }
+}
+
+impl fmt::Display for GardenReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut lines = vec![];
+
+        for seed in &self.unlocked {
+            lines.push(format!("Unlocked the {} seed", seed));
+        }
+
+        if self.sacrificed {
+            lines.push("Sacrificed the garden for sugar lumps".to_string());
+        }
+
+        if let Some(soil) = &self.soil {
+            lines.push(format!("Switched soil to {}", soil));
+        }
+
+        lines.push(format!(
+            "Planted {} and harvested {} tiles",
+            self.planted, self.harvested
+        ));
+
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLANTS: [&str; 6] = [
+        "bakerWheat",
+        "thumbcorn",
+        "cronerice",
+        "meddleweed",
+        "crumbspore",
+        "brownMold",
+    ];
+
+    /// Garden with a 3 by 3 plot in the corner of a 4 by 4 grid, where only Baker's wheat is unlocked
+    fn state() -> GardenState {
+        let soils = ["dirt", "fertilizer", "woodchips"]
+            .iter()
+            .map(|key| Soil {
+                key: key.to_string(),
+                name: key.to_string(),
+                unlocked: *key != "woodchips",
+            })
+            .collect();
+
+        let plants = PLANTS
+            .iter()
+            .map(|key| Plant {
+                key: key.to_string(),
+                name: key.to_string(),
+                mature: 50.0,
+                unlocked: *key == "bakerWheat",
+            })
+            .collect();
+
+        let plot = (0..4)
+            .map(|y| {
+                (0..4)
+                    .map(|x| Tile {
+                        unlocked: x > 0 && y > 0,
+                        plant: None,
+                        age: 0.0,
+                    })
+                    .collect()
+            })
+            .collect();
+
+        GardenState {
+            soil: 0,
+            soil_ready: true,
+            soils,
+            plants,
+            plot,
+        }
+    }
+
+    #[test]
+    fn plant_mutation_layout() {
+        let garden = Garden {
+            goal: GardenGoal::UnlockSeeds,
+            sacrifice: false,
+        };
+
+        let actions = garden.plan(&state());
+
+        assert_eq!(actions[0], GardenAction::SetSoil(1));
+
+        let planted: Vec<(usize, usize)> = actions
+            .iter()
+            .filter_map(|action| match action {
+                GardenAction::Plant { x, y, plant: 0 } => Some((*x, *y)),
+                _ => None,
+            })
+            .collect();
+
+        // Every empty tile touches two Baker's wheat for Thumbcorn to grow
+        assert_eq!(planted, [(1, 1), (3, 1), (2, 2), (3, 2), (1, 3), (2, 3)]);
+    }
+
+    #[test]
+    fn harvest_new_seeds_and_weeds() {
+        let garden = Garden {
+            goal: GardenGoal::UnlockSeeds,
+            sacrifice: false,
+        };
+        let mut state = state();
+
+        // Young Thumbcorn is left to mature, mature one is harvested to unlock it
+        state.plot[1][2] = Tile {
+            unlocked: true,
+            plant: Some(1),
+            age: 10.0,
+        };
+        state.plot[2][1] = Tile {
+            unlocked: true,
+            plant: Some(1),
+            age: 60.0,
+        };
+        // Young meddleweed is kept while it may drop locked seeds
+        state.plot[3][2] = Tile {
+            unlocked: true,
+            plant: Some(3),
+            age: 10.0,
+        };
+
+        let harvested: Vec<GardenAction> = garden
+            .plan(&state)
+            .into_iter()
+            .filter(|action| matches!(action, GardenAction::Harvest { .. }))
+            .collect();
+        assert_eq!(
+            harvested,
+            [GardenAction::Harvest {
+                x: 1,
+                y: 2,
+                plant: 1
+            }]
+        );
+
+        // Once spores are unlocked, meddleweed is pulled right away
+        for plant in &mut state.plants {
+            plant.unlocked = true;
+        }
+
+        assert!(garden.plan(&state).contains(&GardenAction::Harvest {
+            x: 2,
+            y: 3,
+            plant: 3
+        }));
+
+        let garden = Garden {
+            goal: GardenGoal::UnlockSeeds,
+            sacrifice: true,
+        };
+        assert_eq!(garden.plan(&state), [GardenAction::Sacrifice]);
+    }
+
+    #[test]
+    fn render_plot() {
+        let mut state = state();
+        state.plot[1][1].plant = Some(0);
+        state.plot[1][1].age = 60.0;
+        state.plot[2][2].plant = Some(0);
+        state.plot[3][3].plant = Some(3);
+
+        assert_eq!(
+            state.render(),
+            "Soil: dirt\n\nA..\n.a.\n..b\n\nA: bakerWheat\nB: meddleweed"
+        );
+
+        // Plants past the alphabet share `?`
+        state.plants = (0..28)
+            .map(|plant| Plant {
+                key: format!("plant{}", plant),
+                name: format!("plant{}", plant),
+                mature: 50.0,
+                unlocked: true,
+            })
+            .collect();
+        state.plot = (0..6)
+            .map(|y| {
+                (0..6)
+                    .map(|x| Tile {
+                        unlocked: true,
+                        plant: Some((y * 6 + x) % 28),
+                        age: 0.0,
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let rendered = state.render();
+        assert!(rendered
+            .starts_with("Soil: dirt\n\nabcdef\nghijkl\nmnopqr\nstuvwx\nyz??ab\ncdefgh\n\n"));
+        assert!(rendered.ends_with("Z: plant25\n?: plant26, plant27"));
+
+        assert_eq!(
+            "unlock_seeds".parse::<GardenGoal>().unwrap().to_string(),
+            "unlock_seeds"
+        );
+    }
+}
@@ -0,0 +1,252 @@
+use std::{env, fmt, str::FromStr};
+
+use chrono::{DateTime, Duration, Utc};
+use serde_json::Value;
+
+use super::buffs::{Buffs, BUILDING_SPECIAL, FRENZY};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Spell {
+    /// Spawns a golden cookie, or a wrath cookie when it backfires
+    HandOfFate,
+    /// Gives half an hour of production, or takes 15 minutes of it when it backfires
+    ConjureBakedGoods,
+}
+
+impl Spell {
+    /// Key of the spell in `M.spells`
+    pub fn key(&self) -> &'static str {
+        match self {
+            Self::HandOfFate => "hand of fate",
+            Self::ConjureBakedGoods => "conjure baked goods",
+        }
+    }
+}
+
+impl FromStr for Spell {
+    type Err = ();
+
+    fn from_str(key: &str) -> Result<Self, Self::Err> {
+        match key {
+            "hand of fate" => Ok(Self::HandOfFate),
+            "conjure baked goods" => Ok(Self::ConjureBakedGoods),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Spell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::HandOfFate => "Force the Hand of Fate",
+            Self::ConjureBakedGoods => "Conjure Baked Goods",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+/// State of the Wizard tower minigame
+#[derive(Debug, Clone, PartialEq)]
+pub struct GrimoireState {
+    pub magic: f64,
+    pub max_magic: f64,
+    pub hand_of_fate_cost: f64,
+    pub conjure_cost: f64,
+    pub buffs: Buffs,
+}
+
+impl GrimoireState {
+    /// Parse the state returned by the game
+    pub fn from_json(state: &Value, buffs: Buffs) -> Option<Self> {
+        Some(Self {
+            magic: state["magic"].as_f64()?,
+            max_magic: state["max_magic"].as_f64()?,
+            hand_of_fate_cost: state["hand_of_fate_cost"].as_f64()?,
+            conjure_cost: state["conjure_cost"].as_f64()?,
+            buffs,
+        })
+    }
+
+    fn cost(&self, spell: Spell) -> f64 {
+        match spell {
+            Spell::HandOfFate => self.hand_of_fate_cost,
+            Spell::ConjureBakedGoods => self.conjure_cost,
+        }
+    }
+}
+
+/// Outcome of a spell cast by the bot
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpellCast {
+    pub spell: Spell,
+    pub backfired: bool,
+    /// Effect forced on the golden cookie spawned by Force the Hand of Fate
+    pub effect: Option<String>,
+    /// Cookies gained, negative when lost
+    pub cookies: f64,
+    pub cast_at: DateTime<Utc>,
+}
+
+impl fmt::Display for SpellCast {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Cast {}{}",
+            self.spell,
+            if self.backfired { ", it backfired" } else { "" }
+        )?;
+
+        if let Some(effect) = &self.effect {
+            write!(f, ", spawning {}", effect)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// When to cast spells
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GrimoireMode {
+    Off,
+    /// Force the Hand of Fate while Frenzy is active
+    FateOnFrenzy,
+    /// Force the Hand of Fate while both Frenzy and a building special are active
+    Combo,
+    /// Conjure Baked Goods at most every number of minutes
+    Conjure(i64),
+}
+
+impl FromStr for GrimoireMode {
+    type Err = ();
+
+    /// Parse `off`, `fate_on_frenzy`, `combo` or `conjure:<minutes>`
+    fn from_str(mode: &str) -> Result<Self, Self::Err> {
+        match mode.split_once(':') {
+            None if mode == "off" => Ok(Self::Off),
+            None if mode == "fate_on_frenzy" => Ok(Self::FateOnFrenzy),
+            None if mode == "combo" => Ok(Self::Combo),
+            Some(("conjure", minutes)) => minutes
+                .parse()
+                .ok()
+                .filter(|minutes| *minutes >= 0)
+                .map(Self::Conjure)
+                .ok_or(()),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for GrimoireMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Off => write!(f, "off"),
+            Self::FateOnFrenzy => write!(f, "fate_on_frenzy"),
+            Self::Combo => write!(f, "combo"),
+            Self::Conjure(minutes) => write!(f, "conjure:{}", minutes),
+        }
+    }
+}
+
+/// Settings of the spell casting task
+#[derive(Debug, Clone, PartialEq)]
+pub struct Grimoire {
+    pub mode: GrimoireMode,
+    /// Last time Conjure Baked Goods was cast, to follow its schedule
+    pub last_conjure: Option<DateTime<Utc>>,
+}
+
+impl Grimoire {
+    /// Load the mode from env `GRIMOIRE_MODE`
+    pub fn from_env() -> Self {
+        let mode = match env::var("GRIMOIRE_MODE") {
+            Ok(mode) => mode.parse().expect("Invalid env GRIMOIRE_MODE"),
+            Err(_) => GrimoireMode::Off,
+        };
+
+        Self {
+            mode,
+            last_conjure: None,
+        }
+    }
+
+    /// Spell to cast now, if its conditions are met and there is enough magic
+    pub fn choose(&self, state: &GrimoireState, now: DateTime<Utc>) -> Option<Spell> {
+        let spell = match self.mode {
+            GrimoireMode::Off => return None,
+            GrimoireMode::FateOnFrenzy => state.buffs.has(FRENZY).then_some(Spell::HandOfFate),
+            GrimoireMode::Combo => (state.buffs.has(FRENZY) && state.buffs.has(BUILDING_SPECIAL))
+                .then_some(Spell::HandOfFate),
+            GrimoireMode::Conjure(minutes) => self
+                .last_conjure
+                .is_none_or(|last| now - last >= Duration::minutes(minutes))
+                .then_some(Spell::ConjureBakedGoods),
+        }?;
+
+        (state.magic >= state.cost(spell)).then_some(spell)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(magic: f64, buffs: &[&str]) -> GrimoireState {
+        GrimoireState {
+            magic,
+            max_magic: 100.0,
+            hand_of_fate_cost: 60.0,
+            conjure_cost: 30.0,
+            buffs: buffs.iter().copied().collect(),
+        }
+    }
+
+    #[test]
+    fn cast_during_buffs() {
+        let mut grimoire = Grimoire {
+            mode: "fate_on_frenzy".parse().unwrap(),
+            last_conjure: None,
+        };
+        let now = Utc::now();
+
+        assert_eq!(grimoire.choose(&state(100.0, &[]), now), None);
+        assert_eq!(
+            grimoire.choose(&state(100.0, &[FRENZY]), now),
+            Some(Spell::HandOfFate)
+        );
+        assert_eq!(grimoire.choose(&state(50.0, &[FRENZY]), now), None);
+
+        grimoire.mode = GrimoireMode::Combo;
+        assert_eq!(grimoire.choose(&state(100.0, &[FRENZY]), now), None);
+        assert_eq!(
+            grimoire.choose(&state(100.0, &[FRENZY, BUILDING_SPECIAL]), now),
+            Some(Spell::HandOfFate)
+        );
+    }
+
+    #[test]
+    fn conjure_on_schedule() {
+        let mut grimoire = Grimoire {
+            mode: "conjure:30".parse().unwrap(),
+            last_conjure: None,
+        };
+        let now = Utc::now();
+
+        assert_eq!(
+            grimoire.choose(&state(30.0, &[]), now),
+            Some(Spell::ConjureBakedGoods)
+        );
+
+        grimoire.last_conjure = Some(now - Duration::minutes(10));
+        assert_eq!(grimoire.choose(&state(30.0, &[]), now), None);
+
+        grimoire.last_conjure = Some(now - Duration::minutes(30));
+        assert_eq!(
+            grimoire.choose(&state(30.0, &[]), now),
+            Some(Spell::ConjureBakedGoods)
+        );
+
+        assert_eq!("conjure:-1".parse::<GrimoireMode>(), Err(()));
+        assert_eq!("off".parse(), Ok(GrimoireMode::Off));
+    }
+}
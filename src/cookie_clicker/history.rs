@@ -0,0 +1,433 @@
+use std::{
+    collections::HashMap,
+    env,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection};
+
+use super::{
+    auto_buy::Purchase,
+    grimoire::{Spell, SpellCast},
+    market::{Position, Trade, TradeSide},
+    migrations,
+    shimmers::CollectedShimmer,
+    wrinklers::WrinklerPop,
+};
+
+#[derive(Debug)]
+pub enum HistoryError {
+    RusqliteError(rusqlite::Error),
+    /// A row holds a value this version does not know about
+    InvalidRow(String),
+}
+
+pub type HistoryResult<T> = Result<T, HistoryError>;
+
+/// Record of what happened in the game, kept in its own SQLite database so
+/// it does not depend on the backup store
+#[derive(Debug)]
+pub struct History {
+    connection: Connection,
+}
+
+impl History {
+    pub fn open(path: impl AsRef<Path>) -> HistoryResult<Self> {
+        let connection = Connection::open(path).map_err(HistoryError::RusqliteError)?;
+
+        Self::with_connection(connection)
+    }
+
+    /// Open `history.db` in `PERSISTENT_DATA_PATH`
+    pub fn from_env() -> HistoryResult<Self> {
+        let data_path = env::var("PERSISTENT_DATA_PATH").expect("Missing env PERSISTENT_DATA_PATH");
+
+        Self::open(PathBuf::from(data_path).join("history.db"))
+    }
+
+    /// Create a history on top of an already opened database, migrating it to the latest schema
+    pub fn with_connection(mut connection: Connection) -> HistoryResult<Self> {
+        migrations::migrate_history(&mut connection).map_err(HistoryError::RusqliteError)?;
+
+        Ok(Self { connection })
+    }
+
+    pub fn record_shimmer(&mut self, shimmer: &CollectedShimmer) -> HistoryResult<()> {
+        self.connection
+            .execute(
+                include_str!("./sql/insert_shimmer.sql"),
+                params![shimmer.kind.as_str(), shimmer.effect, shimmer.collected_at],
+            )
+            .map_err(HistoryError::RusqliteError)?;
+
+        Ok(())
+    }
+
+    /// Latest collected shimmers, newest first
+    pub fn recent_shimmers(&mut self, limit: usize) -> HistoryResult<Vec<CollectedShimmer>> {
+        let rows = self
+            .connection
+            .prepare(include_str!("./sql/get_recent_shimmers.sql"))
+            .map_err(HistoryError::RusqliteError)?
+            .query_map(params![limit], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })
+            .map_err(HistoryError::RusqliteError)?
+            .collect::<Result<Vec<(String, String, DateTime<Utc>)>, _>>()
+            .map_err(HistoryError::RusqliteError)?;
+
+        rows.into_iter()
+            .map(|(kind, effect, collected_at)| {
+                Ok(CollectedShimmer {
+                    kind: kind.parse().map_err(|_| HistoryError::InvalidRow(kind))?,
+                    effect,
+                    collected_at,
+                })
+            })
+            .collect()
+    }
+
+    /// How many times each effect was collected since `instant`, most frequent first
+    pub fn shimmer_effects_since(
+        &mut self,
+        instant: DateTime<Utc>,
+    ) -> HistoryResult<Vec<(String, usize)>> {
+        self.connection
+            .prepare(include_str!("./sql/count_shimmer_effects.sql"))
+            .map_err(HistoryError::RusqliteError)?
+            .query_map(params![instant], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(HistoryError::RusqliteError)?
+            .collect::<Result<_, _>>()
+            .map_err(HistoryError::RusqliteError)
+    }
+
+    pub fn record_purchase(&mut self, purchase: &Purchase) -> HistoryResult<()> {
+        self.connection
+            .execute(
+                include_str!("./sql/insert_purchase.sql"),
+                params![
+                    purchase.kind.as_str(),
+                    purchase.name,
+                    purchase.price,
+                    purchase.cps_gain,
+                    purchase.bought_at
+                ],
+            )
+            .map_err(HistoryError::RusqliteError)?;
+
+        Ok(())
+    }
+
+    /// Latest purchases of the auto-buy engine, newest first
+    pub fn recent_purchases(&mut self, limit: usize) -> HistoryResult<Vec<Purchase>> {
+        let rows = self
+            .connection
+            .prepare(include_str!("./sql/get_recent_purchases.sql"))
+            .map_err(HistoryError::RusqliteError)?
+            .query_map(params![limit], |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                ))
+            })
+            .map_err(HistoryError::RusqliteError)?
+            .collect::<Result<Vec<(String, String, f64, f64, DateTime<Utc>)>, _>>()
+            .map_err(HistoryError::RusqliteError)?;
+
+        rows.into_iter()
+            .map(|(kind, name, price, cps_gain, bought_at)| {
+                Ok(Purchase {
+                    kind: kind.parse().map_err(|_| HistoryError::InvalidRow(kind))?,
+                    name,
+                    price,
+                    cps_gain,
+                    bought_at,
+                })
+            })
+            .collect()
+    }
+
+    pub fn record_wrinkler_pop(&mut self, pop: &WrinklerPop) -> HistoryResult<()> {
+        self.connection
+            .execute(
+                include_str!("./sql/insert_wrinkler_pop.sql"),
+                params![pop.wrinklers, pop.reclaimed, pop.popped_at],
+            )
+            .map_err(HistoryError::RusqliteError)?;
+
+        Ok(())
+    }
+
+    /// Wrinklers popped since `instant` and the cookies they gave back
+    pub fn wrinkler_pops_since(&mut self, instant: DateTime<Utc>) -> HistoryResult<(usize, f64)> {
+        self.connection
+            .query_row(
+                include_str!("./sql/sum_wrinkler_pops.sql"),
+                params![instant],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .map_err(HistoryError::RusqliteError)
+    }
+
+    pub fn record_trade(&mut self, trade: &Trade) -> HistoryResult<()> {
+        self.connection
+            .execute(
+                include_str!("./sql/insert_market_trade.sql"),
+                params![
+                    trade.symbol,
+                    trade.side.as_str(),
+                    trade.quantity,
+                    trade.price,
+                    trade.profit,
+                    trade.traded_at
+                ],
+            )
+            .map_err(HistoryError::RusqliteError)?;
+
+        Ok(())
+    }
+
+    /// Positions of the trading bot by stock symbol, replayed from every recorded trade
+    pub fn market_positions(&mut self) -> HistoryResult<HashMap<String, Position>> {
+        let rows = self
+            .connection
+            .prepare(include_str!("./sql/get_market_trades.sql"))
+            .map_err(HistoryError::RusqliteError)?
+            .query_map([], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })
+            .map_err(HistoryError::RusqliteError)?
+            .collect::<Result<Vec<(String, String, i64, f64)>, _>>()
+            .map_err(HistoryError::RusqliteError)?;
+
+        let mut positions: HashMap<String, Position> = HashMap::new();
+
+        for (symbol, side, quantity, price) in rows {
+            let side: TradeSide = side.parse().map_err(|_| HistoryError::InvalidRow(side))?;
+
+            positions
+                .entry(symbol)
+                .or_default()
+                .apply(side, quantity, price);
+        }
+
+        Ok(positions)
+    }
+
+    /// Dollars gained by the trading bot since `instant`
+    pub fn market_profit_since(&mut self, instant: DateTime<Utc>) -> HistoryResult<f64> {
+        self.connection
+            .query_row(
+                include_str!("./sql/sum_market_profit.sql"),
+                params![instant],
+                |row| row.get(0),
+            )
+            .map_err(HistoryError::RusqliteError)
+    }
+
+    pub fn record_spell_cast(&mut self, cast: &SpellCast) -> HistoryResult<()> {
+        self.connection
+            .execute(
+                include_str!("./sql/insert_spell_cast.sql"),
+                params![
+                    cast.spell.key(),
+                    cast.backfired,
+                    cast.effect,
+                    cast.cookies,
+                    cast.cast_at
+                ],
+            )
+            .map_err(HistoryError::RusqliteError)?;
+
+        Ok(())
+    }
+
+    /// How many times each spell was cast since `instant` and how many of those backfired
+    pub fn spell_casts_since(
+        &mut self,
+        instant: DateTime<Utc>,
+    ) -> HistoryResult<Vec<(Spell, usize, usize)>> {
+        let rows = self
+            .connection
+            .prepare(include_str!("./sql/count_spell_casts.sql"))
+            .map_err(HistoryError::RusqliteError)?
+            .query_map(params![instant], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })
+            .map_err(HistoryError::RusqliteError)?
+            .collect::<Result<Vec<(String, usize, usize)>, _>>()
+            .map_err(HistoryError::RusqliteError)?;
+
+        rows.into_iter()
+            .map(|(spell, casts, backfires)| {
+                Ok((
+                    spell.parse().map_err(|_| HistoryError::InvalidRow(spell))?,
+                    casts,
+                    backfires,
+                ))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::super::{auto_buy::ItemKind, shimmers::ShimmerKind};
+    use super::*;
+
+    fn shimmer(kind: ShimmerKind, effect: &str, age: Duration) -> CollectedShimmer {
+        CollectedShimmer {
+            kind,
+            effect: effect.to_string(),
+            collected_at: Utc::now() - age,
+        }
+    }
+
+    #[test]
+    fn record_shimmers() {
+        let mut history = History::with_connection(Connection::open_in_memory().unwrap()).unwrap();
+
+        history
+            .record_shimmer(&shimmer(ShimmerKind::Golden, "frenzy", Duration::days(2)))
+            .unwrap();
+        history
+            .record_shimmer(&shimmer(ShimmerKind::Golden, "frenzy", Duration::hours(2)))
+            .unwrap();
+        history
+            .record_shimmer(&shimmer(ShimmerKind::Wrath, "clot", Duration::hours(1)))
+            .unwrap();
+        history
+            .record_shimmer(&shimmer(ShimmerKind::Golden, "frenzy", Duration::zero()))
+            .unwrap();
+
+        let recent = history.recent_shimmers(2).unwrap();
+        assert_eq!(recent.len(), 2);
+        assert_eq!(recent[0].effect, "frenzy");
+        assert_eq!(recent[1].kind, ShimmerKind::Wrath);
+
+        let effects = history
+            .shimmer_effects_since(Utc::now() - Duration::days(1))
+            .unwrap();
+        assert_eq!(
+            effects,
+            [("frenzy".to_string(), 2), ("clot".to_string(), 1)]
+        );
+    }
+
+    #[test]
+    fn record_purchases() {
+        let mut history = History::with_connection(Connection::open_in_memory().unwrap()).unwrap();
+
+        for (name, age) in [("Cursor", 2), ("Grandma", 1)] {
+            history
+                .record_purchase(&Purchase {
+                    kind: ItemKind::Building,
+                    name: name.to_string(),
+                    price: 100.0,
+                    cps_gain: 1.0,
+                    bought_at: Utc::now() - Duration::minutes(age),
+                })
+                .unwrap();
+        }
+
+        let names: Vec<String> = history
+            .recent_purchases(10)
+            .unwrap()
+            .into_iter()
+            .map(|purchase| purchase.name)
+            .collect();
+        assert_eq!(names, ["Grandma", "Cursor"]);
+    }
+
+    #[test]
+    fn record_wrinkler_pops() {
+        let mut history = History::with_connection(Connection::open_in_memory().unwrap()).unwrap();
+        let since = Utc::now() - Duration::days(1);
+
+        assert_eq!(history.wrinkler_pops_since(since).unwrap(), (0, 0.0));
+
+        for (wrinklers, age) in [
+            (10, Duration::days(2)),
+            (3, Duration::hours(1)),
+            (2, Duration::zero()),
+        ] {
+            history
+                .record_wrinkler_pop(&WrinklerPop {
+                    wrinklers,
+                    reclaimed: 1000.0,
+                    popped_at: Utc::now() - age,
+                })
+                .unwrap();
+        }
+
+        assert_eq!(history.wrinkler_pops_since(since).unwrap(), (5, 2000.0));
+    }
+
+    #[test]
+    fn record_trades() {
+        let mut history = History::with_connection(Connection::open_in_memory().unwrap()).unwrap();
+
+        for (side, quantity, price, profit, age) in [
+            (TradeSide::Buy, 10, 2.0, 0.0, Duration::days(2)),
+            (TradeSide::Sell, 4, 5.0, 12.0, Duration::days(2)),
+            (TradeSide::Sell, 2, 4.0, 4.0, Duration::zero()),
+        ] {
+            history
+                .record_trade(&Trade {
+                    symbol: "CRL".to_string(),
+                    side,
+                    quantity,
+                    price,
+                    profit,
+                    traded_at: Utc::now() - age,
+                })
+                .unwrap();
+        }
+
+        let position = history.market_positions().unwrap()["CRL"];
+        assert_eq!(position.shares, 4);
+        assert_eq!(position.realized, 16.0);
+
+        assert_eq!(
+            history
+                .market_profit_since(Utc::now() - Duration::days(1))
+                .unwrap(),
+            4.0
+        );
+    }
+
+    #[test]
+    fn record_spell_casts() {
+        let mut history = History::with_connection(Connection::open_in_memory().unwrap()).unwrap();
+
+        for (spell, backfired, age) in [
+            (Spell::HandOfFate, false, Duration::days(2)),
+            (Spell::HandOfFate, true, Duration::hours(1)),
+            (Spell::HandOfFate, false, Duration::zero()),
+            (Spell::ConjureBakedGoods, false, Duration::zero()),
+        ] {
+            history
+                .record_spell_cast(&SpellCast {
+                    spell,
+                    backfired,
+                    effect: None,
+                    cookies: 0.0,
+                    cast_at: Utc::now() - age,
+                })
+                .unwrap();
+        }
+
+        assert_eq!(
+            history
+                .spell_casts_since(Utc::now() - Duration::days(1))
+                .unwrap(),
+            [(Spell::ConjureBakedGoods, 1, 0), (Spell::HandOfFate, 2, 1)]
+        );
+    }
+}
@@ -0,0 +1,172 @@
+use std::fmt;
+
+use super::{
+    backup::Backup,
+    save::{SaveData, SaveError},
+};
+
+/// Reason why a backup looks broken
+#[derive(Debug)]
+pub enum IntegrityIssue {
+    /// The save code cannot be decoded, it is usually empty or truncated
+    Undecodable(SaveError),
+    /// A counter that only grows went backwards since the previous backup
+    Regression {
+        counter: &'static str,
+        previous: f64,
+        current: f64,
+    },
+}
+
+impl fmt::Display for IntegrityIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Undecodable(error) => write!(f, "{}", error),
+            Self::Regression {
+                counter,
+                previous,
+                current,
+            } => write!(f, "{} went from {} down to {}", counter, previous, current),
+        }
+    }
+}
+
+/// Compare a save to the previous one, looking for counters going backwards
+///
+/// Cookies baked in the current ascension only reset when ascending, every
+/// other counter checked here never decreases.
+fn find_regression(previous: &SaveData, current: &SaveData) -> Option<IntegrityIssue> {
+    let achievements = |save: &SaveData| save.achievements.iter().filter(|won| **won).count();
+    let ascended = current.stats.resets != previous.stats.resets;
+
+    let counters = [
+        (
+            "ascensions",
+            previous.stats.resets as f64,
+            current.stats.resets as f64,
+            true,
+        ),
+        (
+            "cookies baked in previous ascensions",
+            previous.stats.cookies_reset,
+            current.stats.cookies_reset,
+            true,
+        ),
+        (
+            "cookies baked this ascension",
+            previous.stats.cookies_earned,
+            current.stats.cookies_earned,
+            !ascended,
+        ),
+        (
+            "won achievements",
+            achievements(previous) as f64,
+            achievements(current) as f64,
+            true,
+        ),
+    ];
+
+    for (counter, previous, current, checked) in counters {
+        if checked && current < previous {
+            return Some(IntegrityIssue::Regression {
+                counter,
+                previous,
+                current,
+            });
+        }
+    }
+
+    None
+}
+
+/// Walks backups oldest first and flags the ones that look broken
+///
+/// Each backup is compared to the previous one, so a single regression is only
+/// reported once instead of for every backup taken after it.
+#[derive(Debug, Default)]
+pub struct IntegrityChecker {
+    previous: Option<SaveData>,
+}
+
+impl IntegrityChecker {
+    pub fn check(&mut self, backup: &Backup) -> Option<IntegrityIssue> {
+        let save = match backup.save_data() {
+            Ok(save) => save,
+            Err(error) => return Some(IntegrityIssue::Undecodable(error)),
+        };
+
+        let issue = self
+            .previous
+            .as_ref()
+            .and_then(|previous| find_regression(previous, &save));
+
+        self.previous = Some(save);
+
+        issue
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+
+    const SAVE: &str = include_str!("./test_saves/v2.052.txt");
+
+    fn backup(save: &SaveData) -> Backup {
+        Backup::restore(save.encode(), Utc::now(), None, None)
+    }
+
+    #[test]
+    fn flag_broken_saves() {
+        let mut checker = IntegrityChecker::default();
+        let save = SaveData::decode(SAVE.trim_end()).unwrap();
+
+        assert!(checker.check(&backup(&save)).is_none());
+
+        let empty = Backup::restore(String::new(), Utc::now(), None, None);
+        assert!(matches!(
+            checker.check(&empty),
+            Some(IntegrityIssue::Undecodable(_))
+        ));
+
+        let save_code = save.encode();
+        let truncated = save_code[..save_code.len() / 2].to_string();
+        assert!(matches!(
+            checker.check(&Backup::restore(truncated, Utc::now(), None, None)),
+            Some(IntegrityIssue::Undecodable(_))
+        ));
+
+        // Broken backups are not used as a reference
+        let mut baked_more = save.clone();
+        baked_more.stats.cookies_earned += 1000.0;
+        assert!(checker.check(&backup(&baked_more)).is_none());
+    }
+
+    #[test]
+    fn flag_regressions() {
+        let mut checker = IntegrityChecker::default();
+        let save = SaveData::decode(SAVE.trim_end()).unwrap();
+        checker.check(&backup(&save));
+
+        let mut regressed = save.clone();
+        regressed.stats.cookies_earned /= 2.0;
+        assert!(matches!(
+            checker.check(&backup(&regressed)),
+            Some(IntegrityIssue::Regression {
+                counter: "cookies baked this ascension",
+                ..
+            })
+        ));
+
+        // Only reported once
+        assert!(checker.check(&backup(&regressed)).is_none());
+
+        let mut ascended = regressed.clone();
+        ascended.stats.resets += 1;
+        ascended.stats.cookies_reset += ascended.stats.cookies_earned;
+        ascended.stats.cookies_earned = 0.0;
+        assert!(checker.check(&backup(&ascended)).is_none());
+    }
+}
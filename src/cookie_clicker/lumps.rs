@@ -0,0 +1,240 @@
+use std::{env, fmt};
+
+use serde_json::Value;
+
+use super::save::{building_id, building_name};
+
+/// Unlocking the Farm, Bank, Temple and Wizard tower minigames
+const DEFAULT_LUMP_PRIORITY: &str = "Farm:1,Bank:1,Temple:1,Wizard tower:1";
+
+/// Sugar lump types, as numbered by `Game.lumpCurrentType`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LumpType {
+    Normal,
+    /// Yields 1 or 2 lumps
+    Bifurcated,
+    /// Yields 2 to 7 lumps
+    Golden,
+    /// Yields 0 to 2 lumps
+    Meaty,
+    /// Yields 1 to 3 lumps and may refill a spent lump
+    Caramelized,
+}
+
+impl LumpType {
+    pub fn from_id(id: i64) -> Option<Self> {
+        match id {
+            0 => Some(Self::Normal),
+            1 => Some(Self::Bifurcated),
+            2 => Some(Self::Golden),
+            3 => Some(Self::Meaty),
+            4 => Some(Self::Caramelized),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for LumpType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Normal => "normal",
+            Self::Bifurcated => "bifurcated",
+            Self::Golden => "golden",
+            Self::Meaty => "meaty",
+            Self::Caramelized => "caramelized",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+/// Sugar lump state of a running game
+#[derive(Debug, Clone, PartialEq)]
+pub struct LumpState {
+    pub lumps: f64,
+    pub lump_type: LumpType,
+    /// Milliseconds until the current lump is ripe, negative once it is
+    pub ripe_in: f64,
+    /// Level of each building, by id
+    pub levels: Vec<i64>,
+}
+
+impl LumpState {
+    /// Parse the state returned by the game, `None` while sugar lumps are locked
+    pub fn from_json(state: &Value) -> Option<Self> {
+        if !state["unlocked"].as_bool()? {
+            return None;
+        }
+
+        Some(Self {
+            lumps: state["lumps"].as_f64()?,
+            lump_type: LumpType::from_id(state["type"].as_i64()?)?,
+            ripe_in: state["ripe_in"].as_f64()?,
+            levels: state["levels"]
+                .as_array()?
+                .iter()
+                .map(Value::as_i64)
+                .collect::<Option<_>>()?,
+        })
+    }
+
+    pub fn is_ripe(&self) -> bool {
+        self.ripe_in <= 0.0
+    }
+}
+
+/// What the sugar lump task did
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LumpReport {
+    /// Type of the harvested lump and how many lumps it gave
+    pub harvested: Option<(LumpType, f64)>,
+    /// Buildings levelled up, by id, along with their new level
+    pub levels: Vec<(usize, i64)>,
+}
+
+impl fmt::Display for LumpReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut lines = vec![];
+
+        if let Some((lump_type, lumps)) = self.harvested {
+            lines.push(format!(
+                "Harvested a {} sugar lump, +{} lumps",
+                lump_type, lumps
+            ));
+        }
+
+        for (building, level) in &self.levels {
+            lines.push(format!(
+                "{} upgraded to level {}",
+                building_name(*building),
+                level
+            ));
+        }
+
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+/// Building level to reach
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LevelTarget {
+    pub building: usize,
+    pub level: i64,
+}
+
+/// Spends sugar lumps on building levels, following a priority list
+#[derive(Debug, Clone, PartialEq)]
+pub struct LumpPlanner {
+    pub targets: Vec<LevelTarget>,
+}
+
+impl LumpPlanner {
+    /// Parse a priority list such as `Farm:1,Wizard tower:10`, an empty list never spends lumps
+    pub fn parse(priority: &str) -> Option<Self> {
+        let targets = priority
+            .split(',')
+            .filter(|target| !target.trim().is_empty())
+            .map(|target| {
+                let (building, level) = target.rsplit_once(':')?;
+
+                Some(LevelTarget {
+                    building: building_id(building)?,
+                    level: level.trim().parse().ok()?,
+                })
+            })
+            .collect::<Option<_>>()?;
+
+        Some(Self { targets })
+    }
+
+    /// Load the priority list from env `LUMP_PRIORITY`
+    pub fn from_env() -> Self {
+        let priority =
+            env::var("LUMP_PRIORITY").unwrap_or_else(|_| DEFAULT_LUMP_PRIORITY.to_string());
+
+        Self::parse(&priority).expect("Invalid env LUMP_PRIORITY")
+    }
+
+    /// Building to level up next given the current levels, by id
+    ///
+    /// Targets are reached in order: when the first unreached one is not
+    /// affordable yet, lumps are saved for it rather than spent on later ones.
+    pub fn next_level_up(&self, levels: &[i64], lumps: f64) -> Option<usize> {
+        let target = self.targets.iter().find(|target| {
+            levels
+                .get(target.building)
+                .is_some_and(|level| *level < target.level)
+        })?;
+
+        // Reaching level `n + 1` costs `n + 1` lumps
+        let cost = levels[target.building] + 1;
+
+        (lumps >= cost as f64).then_some(target.building)
+    }
+}
+
+impl fmt::Display for LumpPlanner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let targets: Vec<String> = self
+            .targets
+            .iter()
+            .map(|target| format!("{}:{}", building_name(target.building), target.level))
+            .collect();
+
+        write!(f, "{}", targets.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_priority() {
+        let planner = LumpPlanner::parse("Farm:1, wizard tower:10").unwrap();
+
+        assert_eq!(
+            planner.targets,
+            [
+                LevelTarget {
+                    building: 2,
+                    level: 1
+                },
+                LevelTarget {
+                    building: 7,
+                    level: 10
+                }
+            ]
+        );
+        assert_eq!(planner.to_string(), "Farm:1,Wizard tower:10");
+
+        assert_eq!(LumpPlanner::parse("").unwrap().targets, []);
+        assert_eq!(LumpPlanner::parse("Farm"), None);
+        assert_eq!(LumpPlanner::parse("Unknown:1"), None);
+    }
+
+    #[test]
+    fn spend_lumps_in_order() {
+        let planner = LumpPlanner::parse(DEFAULT_LUMP_PRIORITY).unwrap();
+        let mut levels = vec![0; 20];
+
+        assert_eq!(planner.next_level_up(&levels, 0.0), None);
+        assert_eq!(planner.next_level_up(&levels, 1.0), Some(2));
+
+        levels[2] = 1;
+        assert_eq!(planner.next_level_up(&levels, 1.0), Some(5));
+
+        // Later targets wait for the first unreached one
+        let planner = LumpPlanner::parse("Cursor:3,Farm:1").unwrap();
+        levels[0] = 2;
+        levels[2] = 0;
+        assert_eq!(planner.next_level_up(&levels, 2.0), None);
+        assert_eq!(planner.next_level_up(&levels, 3.0), Some(0));
+
+        levels[0] = 3;
+        assert_eq!(planner.next_level_up(&levels, 1.0), Some(2));
+
+        levels[2] = 1;
+        assert_eq!(planner.next_level_up(&levels, 10.0), None);
+    }
+}
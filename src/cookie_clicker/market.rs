@@ -0,0 +1,372 @@
+use std::{env, fmt, str::FromStr};
+
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+
+use super::{auto_buy::Reserve, settings::env_switch};
+
+/// Stock of the Bank minigame, prices are in dollars
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stock {
+    pub id: usize,
+    pub symbol: String,
+    pub value: f64,
+    /// Value the stock tends to come back to
+    pub resting_value: f64,
+    pub owned: i64,
+    /// Most shares that can be held, given the buildings and warehouses
+    pub max_owned: i64,
+}
+
+impl Stock {
+    pub fn from_json(stock: &Value) -> Option<Self> {
+        Some(Self {
+            id: stock["id"].as_u64()? as usize,
+            symbol: stock["symbol"].as_str()?.to_string(),
+            value: stock["value"].as_f64()?,
+            resting_value: stock["resting_value"].as_f64()?,
+            owned: stock["owned"].as_i64()?,
+            max_owned: stock["max_owned"].as_i64()?,
+        })
+    }
+}
+
+/// State of the Bank minigame
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarketState {
+    pub stocks: Vec<Stock>,
+    /// Buying costs this much more than the stock value, brokers bring it down
+    pub overhead: f64,
+    /// Cookies a dollar is worth, the highest raw production per second
+    pub dollar: f64,
+    pub cookies: f64,
+    pub cps: f64,
+}
+
+impl MarketState {
+    /// Parse the state returned by the game
+    pub fn from_json(state: &Value) -> Option<Self> {
+        Some(Self {
+            stocks: state["stocks"]
+                .as_array()?
+                .iter()
+                .map(Stock::from_json)
+                .collect::<Option<_>>()?,
+            overhead: state["overhead"].as_f64()?,
+            dollar: state["dollar"].as_f64()?,
+            cookies: state["cookies"].as_f64()?,
+            cps: state["cps"].as_f64()?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TradeSide {
+    Buy,
+    Sell,
+}
+
+impl TradeSide {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Buy => "buy",
+            Self::Sell => "sell",
+        }
+    }
+}
+
+impl FromStr for TradeSide {
+    type Err = ();
+
+    fn from_str(side: &str) -> Result<Self, Self::Err> {
+        match side {
+            "buy" => Ok(Self::Buy),
+            "sell" => Ok(Self::Sell),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Trade made by the trading bot
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trade {
+    pub symbol: String,
+    pub side: TradeSide,
+    pub quantity: i64,
+    /// Dollars per share, including the broker overhead when buying
+    pub price: f64,
+    /// Dollars gained over the average buying price, only for sales
+    pub profit: f64,
+    pub traded_at: DateTime<Utc>,
+}
+
+impl fmt::Display for Trade {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.side {
+            TradeSide::Buy => write!(
+                f,
+                "Bought {} {} at ${:.2}",
+                self.quantity, self.symbol, self.price
+            ),
+            TradeSide::Sell => write!(
+                f,
+                "Sold {} {} at ${:.2}, profit ${:.2}",
+                self.quantity, self.symbol, self.price, self.profit
+            ),
+        }
+    }
+}
+
+/// Shares bought by the trading bot and not sold yet, replayed from the ledger
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Position {
+    pub shares: i64,
+    /// Dollars paid for the shares held
+    pub cost: f64,
+    /// Dollars gained by all sales
+    pub realized: f64,
+}
+
+impl Position {
+    /// Dollars paid per share held
+    pub fn average_price(&self) -> f64 {
+        if self.shares > 0 {
+            self.cost / self.shares as f64
+        } else {
+            0.0
+        }
+    }
+
+    /// Add a trade to the position, returning the profit it realized
+    pub fn apply(&mut self, side: TradeSide, quantity: i64, price: f64) -> f64 {
+        match side {
+            TradeSide::Buy => {
+                self.shares += quantity;
+                self.cost += quantity as f64 * price;
+
+                0.0
+            }
+            TradeSide::Sell => {
+                let quantity = quantity.min(self.shares);
+                let average_price = self.average_price();
+                let profit = quantity as f64 * (price - average_price);
+
+                self.shares -= quantity;
+                self.cost -= quantity as f64 * average_price;
+                self.realized += profit;
+
+                profit
+            }
+        }
+    }
+}
+
+/// Threshold strategy of the trading bot
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trading {
+    pub enabled: bool,
+    /// Buy when the value falls under this ratio of the resting value
+    pub buy_below: f64,
+    /// Sell when the value rises above this ratio of the resting value
+    pub sell_above: f64,
+    /// Cookies never spent on stocks
+    pub reserve: Reserve,
+}
+
+impl Trading {
+    /// Load the strategy from env `MARKET_TRADING` (`on` or `off`), `MARKET_BUY_BELOW`, `MARKET_SELL_ABOVE` and `MARKET_RESERVE`
+    pub fn from_env() -> Self {
+        let enabled = env_switch("MARKET_TRADING", false);
+
+        let ratio = |name: &str, default: f64| match env::var(name) {
+            Ok(ratio) => parse_ratio(&ratio).unwrap_or_else(|| panic!("Invalid env {}", name)),
+            Err(_) => default,
+        };
+
+        let reserve = match env::var("MARKET_RESERVE") {
+            Ok(reserve) => reserve.parse().expect("Invalid env MARKET_RESERVE"),
+            Err(_) => Reserve::Lucky,
+        };
+
+        Self {
+            enabled,
+            buy_below: ratio("MARKET_BUY_BELOW", 0.5),
+            sell_above: ratio("MARKET_SELL_ABOVE", 1.5),
+            reserve,
+        }
+    }
+
+    /// What to do with a stock given the shares the bot holds
+    ///
+    /// Only shares bought by the bot are sold, and never at a loss. Purchases
+    /// are capped by the stock limit and by the cookies in bank above the reserve.
+    pub fn decide(
+        &self,
+        stock: &Stock,
+        position: &Position,
+        state: &MarketState,
+    ) -> Option<(TradeSide, i64)> {
+        let sellable = position.shares.min(stock.owned);
+
+        if sellable > 0
+            && stock.value >= self.sell_above * stock.resting_value
+            && stock.value > position.average_price()
+        {
+            return Some((TradeSide::Sell, sellable));
+        }
+
+        if stock.value <= self.buy_below * stock.resting_value {
+            let share_cost = stock.value * state.overhead * state.dollar;
+            let available = state.cookies - self.reserve.cookies(state.cps);
+            let affordable = if share_cost > 0.0 && available > 0.0 {
+                (available / share_cost).floor() as i64
+            } else {
+                0
+            };
+            let quantity = (stock.max_owned - stock.owned).min(affordable);
+
+            if quantity > 0 {
+                return Some((TradeSide::Buy, quantity));
+            }
+        }
+
+        None
+    }
+}
+
+impl fmt::Display for Trading {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}, buying under {}% and selling over {}% of resting values, reserve: {}",
+            if self.enabled { "running" } else { "paused" },
+            self.buy_below * 100.0,
+            self.sell_above * 100.0,
+            self.reserve
+        )
+    }
+}
+
+/// Parse a positive ratio of the resting value
+pub fn parse_ratio(ratio: &str) -> Option<f64> {
+    ratio
+        .parse()
+        .ok()
+        .filter(|ratio: &f64| ratio.is_finite() && *ratio > 0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stock(value: f64, owned: i64) -> Stock {
+        Stock {
+            id: 0,
+            symbol: "CRL".to_string(),
+            value,
+            resting_value: 10.0,
+            owned,
+            max_owned: 100,
+        }
+    }
+
+    fn state(cookies: f64) -> MarketState {
+        MarketState {
+            stocks: vec![],
+            overhead: 1.2,
+            dollar: 1.0,
+            cookies,
+            cps: 1.0,
+        }
+    }
+
+    #[test]
+    fn replay_position() {
+        let mut position = Position::default();
+
+        assert_eq!(position.apply(TradeSide::Buy, 10, 2.0), 0.0);
+        assert_eq!(position.apply(TradeSide::Buy, 10, 4.0), 0.0);
+        assert_eq!(position.average_price(), 3.0);
+
+        assert_eq!(position.apply(TradeSide::Sell, 5, 5.0), 10.0);
+        assert_eq!(position.shares, 15);
+        assert_eq!(position.average_price(), 3.0);
+
+        // Shares not bought by the bot don't count
+        assert_eq!(position.apply(TradeSide::Sell, 20, 1.0), -30.0);
+        assert_eq!(position.shares, 0);
+        assert_eq!(position.realized, -20.0);
+    }
+
+    #[test]
+    fn trade_on_thresholds() {
+        let trading = Trading {
+            enabled: true,
+            buy_below: 0.5,
+            sell_above: 1.5,
+            reserve: Reserve::None,
+        };
+        let mut position = Position::default();
+
+        // Limited by the cookies in bank, then by the stock limit
+        assert_eq!(
+            trading.decide(&stock(5.0, 0), &position, &state(60.0)),
+            Some((TradeSide::Buy, 10))
+        );
+        assert_eq!(
+            trading.decide(&stock(5.0, 95), &position, &state(6000.0)),
+            Some((TradeSide::Buy, 5))
+        );
+        assert_eq!(
+            trading.decide(&stock(6.0, 0), &position, &state(6000.0)),
+            None
+        );
+
+        // Shares bought by hand are left alone
+        assert_eq!(
+            trading.decide(&stock(16.0, 50), &position, &state(0.0)),
+            None
+        );
+
+        position.apply(TradeSide::Buy, 10, 6.0);
+        assert_eq!(
+            trading.decide(&stock(16.0, 50), &position, &state(0.0)),
+            Some((TradeSide::Sell, 10))
+        );
+
+        // Never sell at a loss
+        position.apply(TradeSide::Buy, 10, 30.0);
+        assert_eq!(
+            trading.decide(&stock(16.0, 50), &position, &state(0.0)),
+            None
+        );
+
+        assert_eq!(parse_ratio("0.8"), Some(0.8));
+    }
+
+    #[test]
+    fn keep_reserve_when_buying() {
+        let trading = Trading {
+            enabled: true,
+            buy_below: 0.5,
+            sell_above: 1.5,
+            reserve: Reserve::Seconds(30.0),
+        };
+        let position = Position::default();
+
+        // Only the 30 cookies above the reserve of 30 seconds of production are spent
+        assert_eq!(
+            trading.decide(&stock(5.0, 0), &position, &state(60.0)),
+            Some((TradeSide::Buy, 5))
+        );
+        assert_eq!(
+            trading.decide(&stock(5.0, 0), &position, &state(30.0)),
+            None
+        );
+        assert_eq!(
+            trading.decide(&stock(5.0, 0), &position, &state(10.0)),
+            None
+        );
+        assert_eq!(parse_ratio("-1"), None);
+    }
+}
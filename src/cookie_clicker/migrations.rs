@@ -0,0 +1,229 @@
+use rusqlite::{params, Connection, Transaction};
+
+use super::compression::{compress_save_code, content_hash};
+
+enum Migration {
+    Sql(&'static str),
+    /// Migration that needs to transform data in Rust
+    Rust(fn(&Transaction) -> rusqlite::Result<()>),
+}
+
+/// Schema migrations of the backups database, `MIGRATIONS[i]` upgrades it from version `i` to `i + 1`
+const MIGRATIONS: [Migration; 5] = [
+    Migration::Sql(include_str!("./sql/migrations/0001_create_backups.sql")),
+    Migration::Sql(include_str!("./sql/migrations/0002_add_backup_metrics.sql")),
+    // Runs the statements of `0003_compress_backups/` around the data transformation
+    Migration::Rust(compress_backups),
+    Migration::Sql(include_str!("./sql/migrations/0004_add_backup_labels.sql")),
+    Migration::Sql(include_str!(
+        "./sql/migrations/0005_add_backup_metrics_unavailable.sql"
+    )),
+];
+
+/// Schema migrations of the history database, numbered separately
+const HISTORY_MIGRATIONS: [Migration; 5] = [
+    Migration::Sql(include_str!(
+        "./sql/migrations/history/0001_create_shimmers.sql"
+    )),
+    Migration::Sql(include_str!(
+        "./sql/migrations/history/0002_create_purchases.sql"
+    )),
+    Migration::Sql(include_str!(
+        "./sql/migrations/history/0003_create_wrinkler_pops.sql"
+    )),
+    Migration::Sql(include_str!(
+        "./sql/migrations/history/0004_create_market_trades.sql"
+    )),
+    Migration::Sql(include_str!(
+        "./sql/migrations/history/0005_create_spell_casts.sql"
+    )),
+];
+
+/// Schema version of a fully migrated backups database
+pub const LATEST_VERSION: usize = MIGRATIONS.len();
+
+/// Get the schema version, stored in `PRAGMA user_version`
+pub fn schema_version(connection: &Connection) -> rusqlite::Result<usize> {
+    connection.query_row("PRAGMA user_version", [], |row| row.get(0))
+}
+
+/// Apply the migrations the backups database has not seen yet
+pub fn migrate(connection: &mut Connection) -> rusqlite::Result<()> {
+    apply(connection, &MIGRATIONS)
+}
+
+/// Apply the migrations the history database has not seen yet
+pub fn migrate_history(connection: &mut Connection) -> rusqlite::Result<()> {
+    apply(connection, &HISTORY_MIGRATIONS)
+}
+
+/// Apply the migrations a database has not seen yet, each one in its own transaction
+fn apply(connection: &mut Connection, migrations: &[Migration]) -> rusqlite::Result<()> {
+    let version = schema_version(connection)?;
+
+    for (index, migration) in migrations.iter().enumerate().skip(version) {
+        let transaction = connection.transaction()?;
+
+        match migration {
+            Migration::Sql(migration) => transaction.execute_batch(migration)?,
+            Migration::Rust(migration) => migration(&transaction)?,
+        }
+
+        transaction.pragma_update(None, "user_version", index + 1)?;
+
+        transaction.commit()?;
+    }
+
+    Ok(())
+}
+
+/// Compress plain text save codes and record their content hash, rebuilding the table to store blobs
+fn compress_backups(transaction: &Transaction) -> rusqlite::Result<()> {
+    transaction.execute_batch(include_str!(
+        "./sql/migrations/0003_compress_backups/create_compressed_backups.sql"
+    ))?;
+
+    let save_codes = transaction
+        .prepare("SELECT id, save_code FROM compressed_backups")?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<Vec<(i64, String)>, _>>()?;
+
+    for (id, save_code) in save_codes {
+        transaction.execute(
+            "UPDATE compressed_backups SET save_code = ?2, content_hash = ?3 WHERE id = ?1",
+            params![id, compress_save_code(&save_code), content_hash(&save_code)],
+        )?;
+    }
+
+    transaction.execute_batch(include_str!(
+        "./sql/migrations/0003_compress_backups/replace_backups.sql"
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{
+        compression::decompress_save_code,
+        store::{BackupStore, SqliteStore},
+    };
+    use super::*;
+
+    /// Schema created by `Backups` before migrations were introduced
+    const UNVERSIONED_SCHEMA: &str = r#"CREATE TABLE IF NOT EXISTS "backups" (
+	"id" INTEGER NOT NULL UNIQUE,
+	"save_code" TEXT NOT NULL,
+	"created_at" TEXT NOT NULL,
+	PRIMARY KEY("id" AUTOINCREMENT)
+);
+
+CREATE INDEX IF NOT EXISTS "backups_created_at" ON "backups" ("created_at" DESC);"#;
+
+    fn unversioned_database() -> Connection {
+        let connection = Connection::open_in_memory().unwrap();
+
+        // `execute` only ran the first statement, so these databases have no index
+        connection.execute(UNVERSIONED_SCHEMA, []).unwrap();
+        connection
+            .execute(
+                "INSERT INTO backups (save_code, created_at) VALUES (?1, ?2)",
+                ["save code", "2023-04-01 12:00:00.000+00:00"],
+            )
+            .unwrap();
+
+        connection
+    }
+
+    #[test]
+    fn migrate_empty_database() {
+        let mut connection = Connection::open_in_memory().unwrap();
+
+        migrate(&mut connection).unwrap();
+
+        assert_eq!(schema_version(&connection).unwrap(), LATEST_VERSION);
+    }
+
+    #[test]
+    fn migrate_unversioned_database() {
+        let mut connection = unversioned_database();
+        assert_eq!(schema_version(&connection).unwrap(), 0);
+
+        migrate(&mut connection).unwrap();
+
+        assert_eq!(schema_version(&connection).unwrap(), LATEST_VERSION);
+
+        let (save_code, hash, created_at): (Vec<u8>, String, String) = connection
+            .query_row(
+                "SELECT save_code, content_hash, created_at FROM backups",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!(decompress_save_code(&save_code).unwrap(), "save code");
+        assert_eq!(hash, content_hash("save code"));
+        assert_eq!(created_at, "2023-04-01 12:00:00.000+00:00");
+
+        let index_count: usize = connection
+            .query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE type = 'index' AND name = 'backups_created_at'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(index_count, 1);
+    }
+
+    #[test]
+    fn backfill_metrics_once() {
+        let path = std::env::temp_dir().join(format!("metrics-{}.db", std::process::id()));
+        let save_code = include_str!("./test_saves/v2.052.txt").trim_end();
+
+        {
+            let connection = Connection::open(&path).unwrap();
+            connection.execute(UNVERSIONED_SCHEMA, []).unwrap();
+
+            for save_code in [save_code, "undecodable"] {
+                connection
+                    .execute(
+                        "INSERT INTO backups (save_code, created_at) VALUES (?1, ?2)",
+                        [save_code, "2023-04-01 12:00:00.000+00:00"],
+                    )
+                    .unwrap();
+            }
+        }
+
+        let mut store = SqliteStore::open(&path, None).unwrap();
+        assert!(store.get(1).unwrap().unwrap().metrics.is_some());
+        assert!(store.get(2).unwrap().unwrap().metrics.is_none());
+
+        // Only the undecodable save is flagged, so it is not decoded again
+        let connection = Connection::open(&path).unwrap();
+        let flagged: Vec<(i64, bool)> = connection
+            .prepare("SELECT id, metrics_unavailable FROM backups ORDER BY id")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(flagged, [(1, false), (2, true)]);
+
+        let pending: usize = connection
+            .prepare(include_str!("./sql/get_backups_without_metrics.sql"))
+            .unwrap()
+            .query_map([], |_| Ok(()))
+            .unwrap()
+            .count();
+        assert_eq!(pending, 0);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn migrate_twice() {
+        let mut connection = unversioned_database();
+
+        migrate(&mut connection).unwrap();
+        migrate(&mut connection).unwrap();
+
+        assert_eq!(schema_version(&connection).unwrap(), LATEST_VERSION);
+    }
+}
@@ -1,20 +1,96 @@
 use std::{env, num::ParseFloatError};
 
-use log::{info, trace};
+use chrono::Utc;
+use log::{info, trace, warn};
+use serde_json::Value;
 use thirtyfour::{
-    error::WebDriverError, By,
-    DesiredCapabilities, WebDriver, prelude::ElementWaitable
+    error::WebDriverError, prelude::ElementWaitable, By, DesiredCapabilities, WebDriver,
 };
 
 mod tasks;
 pub use tasks::{ConcurrentCookieClicker, CookieClickerTasks};
 
+mod auto_buy;
+use auto_buy::Candidate;
+pub use auto_buy::{AutoBuy, Purchase};
+
+mod auto_click;
+pub use auto_click::{AutoClick, MAX_CLICKS_PER_SECOND};
+
+mod archive;
+pub use archive::{export_archive, import_archive, ArchiveError};
+
 mod backup;
-pub use backup::{Backup, BackupError, Backups};
+pub use backup::{timezone, Backup, BackupError, Backups};
+
+mod buffs;
+use buffs::Buffs;
+
+mod compression;
+
+mod diff;
+pub use diff::SaveDiff;
+
+mod encryption;
+pub use encryption::Encryption;
+
+mod garden;
+pub use garden::{Garden, GardenGoal, GardenReport, GardenState};
+
+mod grimoire;
+use grimoire::Spell;
+pub use grimoire::{Grimoire, GrimoireMode, GrimoireState, SpellCast};
+
+mod history;
+pub use history::{History, HistoryError};
+
+mod integrity;
+
+mod lumps;
+pub use lumps::{LumpPlanner, LumpReport, LumpState};
+
+mod market;
+use market::TradeSide;
+pub use market::{parse_ratio, MarketState, Trade, Trading};
+
+mod migrations;
+
+mod pantheon;
+pub use pantheon::{Loadout, Pantheon, PantheonReport, PantheonState};
+
+mod replication;
+
+mod retention;
+
+mod s3;
+
+mod save;
+pub use save::{beautify, normalize_save_code};
+
+mod settings;
+
+mod shimmers;
+use shimmers::Shimmer;
+pub use shimmers::{CollectedShimmer, WrathPolicy};
+
+mod store;
+
+mod wrinklers;
+use wrinklers::Wrinkler;
+pub use wrinklers::{WrinklerManagement, WrinklerPop};
 
 pub struct CookieClicker {
     driver: Option<WebDriver>,
     pub backups: Backups,
+    pub auto_click: AutoClick,
+    pub auto_buy: AutoBuy,
+    pub wrinklers: WrinklerManagement,
+    pub lump_planner: LumpPlanner,
+    pub garden: Garden,
+    pub trading: Trading,
+    pub grimoire: Grimoire,
+    pub pantheon: Pantheon,
+    pub history: History,
 }
 
 #[derive(Debug)]
@@ -26,6 +102,7 @@ pub enum CookieClickerError {
     ParseFloat(ParseFloatError),
     DriverNotStarted,
     BackupError(BackupError),
+    HistoryError(HistoryError),
 }
 
 pub type CookieClickerResult<T> = Result<T, CookieClickerError>;
@@ -36,10 +113,20 @@ impl CookieClicker {
     /// Create a new `CookieClicker` object
     pub fn new() -> CookieClickerResult<Self> {
         let backups = Backups::new().map_err(CookieClickerError::BackupError)?;
+        let history = History::from_env().map_err(CookieClickerError::HistoryError)?;
 
         Ok(Self {
             driver: None,
             backups,
+            auto_click: AutoClick::from_env(),
+            auto_buy: AutoBuy::from_env(),
+            wrinklers: WrinklerManagement::from_env(),
+            lump_planner: LumpPlanner::from_env(),
+            garden: Garden::from_env(),
+            trading: Trading::from_env(),
+            grimoire: Grimoire::from_env(),
+            pantheon: Pantheon::from_env(),
+            history,
         })
     }
 
@@ -50,6 +137,8 @@ impl CookieClicker {
         self.load_save_code(initial_save).await?;
         self.load_beta().await?;
 
+        self.auto_click.reset_clicks();
+
         Ok(())
     }
 
@@ -88,31 +177,48 @@ impl CookieClicker {
     }
 
     /// Retrieve save code and store on disk for later use
-    pub async fn backup_save_code(&mut self) -> CookieClickerResult<()> {
-        let backup = Backup::new(self.get_save_code().await?);
-        self.backups
+    ///
+    /// Returns `false` when the save code did not change since the latest backup
+    pub async fn backup_save_code(&mut self) -> CookieClickerResult<bool> {
+        let save_code = self.get_save_code().await?;
+        let mut backup = Backup::new(save_code);
+
+        match self.get_cookies_per_second().await {
+            Ok(cookies_per_second) => backup = backup.with_cookies_per_second(cookies_per_second),
+            Err(error) => warn!("Cannot get cookies per second for backup: {:?}", error),
+        }
+
+        let stored = self
+            .backups
             .add(backup)
             .map_err(CookieClickerError::BackupError)?;
 
-        Ok(())
+        let pruned = self
+            .backups
+            .prune()
+            .map_err(CookieClickerError::BackupError)?;
+
+        if pruned > 0 {
+            info!("Pruned {} expired backups", pruned);
+        }
+
+        Ok(stored)
     }
 
     /// Load save code into the current game
     async fn load_save_code(&mut self, initial_save: String) -> CookieClickerResult<()> {
         let driver = self.driver()?;
 
-        let save_script = format!(
-            r#"
+        // Pass the save code as an argument so that it never needs escaping
+        let save_script = r#"
             while (typeof Game.localStorageSet !== "function");
-            return Game.localStorageSet(Game.SaveTo, "{}");
-            "#,
-            initial_save
-        );
+            return Game.localStorageSet(Game.SaveTo, arguments[0]);
+            "#;
 
         trace!("Loading save code...");
 
         driver
-            .execute(&save_script, vec![])
+            .execute(save_script, vec![Value::String(initial_save)])
             .await
             .map_err(CookieClickerError::DriverError)?;
 
@@ -136,6 +242,780 @@ impl CookieClicker {
         Ok(save_code)
     }
 
+    /// Click the big cookie `clicks` times, spread over the next second
+    pub async fn click_big_cookie(&mut self, clicks: u32) -> CookieClickerResult<()> {
+        let driver = self.driver()?;
+
+        // The game ignores clicks too close to each other, so they can't be sent at once.
+        // Clicks are counted by the game, the ones scheduled last time are done by now
+        let click_script = r#"
+            const clicks = arguments[0];
+            for (let i = 0; i < clicks; i++) {
+                setTimeout(() => Game.ClickCookie(), i * 1000 / clicks);
+            }
+            return Game.cookieClicks;
+            "#;
+
+        let cookie_clicks = driver
+            .execute(click_script, vec![Value::from(clicks)])
+            .await
+            .map_err(CookieClickerError::DriverError)?
+            .json()
+            .as_i64();
+
+        if let Some(cookie_clicks) = cookie_clicks {
+            self.auto_click.count_clicks(cookie_clicks);
+        }
+
+        Ok(())
+    }
+
+    /// Pop the golden cookies and reindeer on screen, wrath cookies depending on `policy`
+    ///
+    /// Returns the collected shimmers, which are also recorded to history.
+    pub async fn collect_shimmers(
+        &mut self,
+        policy: WrathPolicy,
+    ) -> CookieClickerResult<Vec<CollectedShimmer>> {
+        let driver = self.driver()?;
+
+        let list_script = r#"
+            return {
+                shimmers: Game.shimmers.map(shimmer => ({
+                    id: shimmer.id,
+                    type: shimmer.type,
+                    wrath: shimmer.wrath,
+                })),
+                buffs: Object.keys(Game.buffs).length,
+            };
+            "#;
+
+        let state = driver
+            .execute(list_script, vec![])
+            .await
+            .map_err(CookieClickerError::DriverError)?
+            .json()
+            .clone();
+
+        let mut buffs = state["buffs"].as_u64().unwrap_or_default() as usize;
+        let shimmers: Vec<Shimmer> = state["shimmers"]
+            .as_array()
+            .map(|shimmers| shimmers.iter().filter_map(Shimmer::from_json).collect())
+            .unwrap_or_default();
+
+        // Golden cookies remember the effect they picked, reindeer always give cookies.
+        // Popping may start a buff, so the buffs are counted again for the next shimmer
+        let pop_script = r#"
+            const shimmer = Game.shimmers.find(shimmer => shimmer.id === arguments[0]);
+            if (!shimmer) return null;
+            shimmer.pop();
+            return {
+                effect: shimmer.type === "golden" ? Game.shimmerTypes.golden.last : "cookies",
+                buffs: Object.keys(Game.buffs).length,
+            };
+            "#;
+
+        let mut collected = vec![];
+
+        for shimmer in shimmers {
+            if !policy.should_pop(shimmer.kind, buffs) {
+                continue;
+            }
+
+            let popped = driver
+                .execute(pop_script, vec![Value::from(shimmer.id)])
+                .await
+                .map_err(CookieClickerError::DriverError)?
+                .json()
+                .clone();
+
+            // The shimmer faded away in the meantime
+            let effect = match popped["effect"].as_str() {
+                Some(effect) => effect.to_string(),
+                None => continue,
+            };
+            buffs = popped["buffs"].as_u64().unwrap_or_default() as usize;
+
+            collected.push(CollectedShimmer {
+                kind: shimmer.kind,
+                effect,
+                collected_at: Utc::now(),
+            });
+        }
+
+        for shimmer in &collected {
+            self.history
+                .record_shimmer(shimmer)
+                .map_err(CookieClickerError::HistoryError)?;
+        }
+
+        Ok(collected)
+    }
+
+    /// Buy the building or upgrade with the shortest payback period, if the strategy allows it
+    ///
+    /// The gain of each candidate is measured by applying it, recalculating the
+    /// production and reverting it. Toggles and research are left to the player,
+    /// as research can start the Grandmapocalypse.
+    pub async fn auto_buy(&mut self) -> CookieClickerResult<Option<Purchase>> {
+        let driver = self.driver()?;
+
+        let candidates_script = r#"
+            const baseCps = Game.cookiesPs;
+            const gain = (apply, revert) => {
+                apply();
+                Game.CalculateGains();
+                const gained = Game.cookiesPs - baseCps;
+                revert();
+                return gained;
+            };
+
+            const buildings = Game.ObjectsById
+                .filter(building => !building.locked)
+                .map(building => ({
+                    kind: "building",
+                    id: building.id,
+                    name: building.name,
+                    price: building.price,
+                    cps_gain: gain(() => building.amount++, () => building.amount--),
+                }));
+            const upgrades = Game.UpgradesInStore
+                .filter(upgrade => !["toggle", "tech", "debug"].includes(upgrade.pool))
+                .map(upgrade => ({
+                    kind: "upgrade",
+                    id: upgrade.id,
+                    name: upgrade.name,
+                    price: upgrade.getPrice(),
+                    cps_gain: gain(() => upgrade.bought = 1, () => upgrade.bought = 0),
+                }));
+            Game.CalculateGains();
+
+            return {
+                cookies: Game.cookies,
+                cps: Game.cookiesPs,
+                candidates: buildings.concat(upgrades),
+            };
+            "#;
+
+        let state = driver
+            .execute(candidates_script, vec![])
+            .await
+            .map_err(CookieClickerError::DriverError)?
+            .json()
+            .clone();
+
+        let cookies = state["cookies"].as_f64().unwrap_or_default();
+        let cps = state["cps"].as_f64().unwrap_or_default();
+        let candidates: Vec<Candidate> = state["candidates"]
+            .as_array()
+            .map(|candidates| candidates.iter().filter_map(Candidate::from_json).collect())
+            .unwrap_or_default();
+
+        let candidate = match self.auto_buy.choose(&candidates, cookies, cps) {
+            Some(candidate) => candidate,
+            None => return Ok(None),
+        };
+
+        let buy_script = r#"
+            const [kind, id] = arguments;
+            Game.buyMode = 1;
+            Game.buyBulk = 1;
+
+            if (kind === "building") {
+                const building = Game.ObjectsById[id];
+                const amount = building.amount;
+                building.buy(1);
+                return building.amount > amount;
+            }
+
+            const upgrade = Game.UpgradesById[id];
+            upgrade.buy(true);
+            return upgrade.bought > 0;
+            "#;
+
+        let bought = driver
+            .execute(
+                buy_script,
+                vec![
+                    Value::from(candidate.kind.as_str()),
+                    Value::from(candidate.id),
+                ],
+            )
+            .await
+            .map_err(CookieClickerError::DriverError)?
+            .json()
+            .as_bool()
+            .unwrap_or_default();
+
+        if !bought {
+            return Ok(None);
+        }
+
+        let purchase = Purchase {
+            kind: candidate.kind,
+            name: candidate.name.clone(),
+            price: candidate.price,
+            cps_gain: candidate.cps_gain,
+            bought_at: Utc::now(),
+        };
+
+        self.history
+            .record_purchase(&purchase)
+            .map_err(CookieClickerError::HistoryError)?;
+
+        Ok(Some(purchase))
+    }
+
+    /// Pop wrinklers according to the wrinkler policy, returns `None` when none was popped
+    pub async fn manage_wrinklers(&mut self) -> CookieClickerResult<Option<WrinklerPop>> {
+        let driver = self.driver()?;
+
+        let list_script = r#"
+            return {
+                wrinklers: Game.wrinklers
+                    .filter(wrinkler => wrinkler.phase > 0)
+                    .map(wrinkler => ({
+                        id: wrinkler.id,
+                        sucked: wrinkler.sucked,
+                        type: wrinkler.type,
+                    })),
+                cookies: Game.cookies,
+                cps: Game.cookiesPs,
+            };
+            "#;
+
+        let state = driver
+            .execute(list_script, vec![])
+            .await
+            .map_err(CookieClickerError::DriverError)?
+            .json()
+            .clone();
+
+        let cookies = state["cookies"].as_f64().unwrap_or_default();
+        let cps = state["cps"].as_f64().unwrap_or_default();
+        let wrinklers: Vec<Wrinkler> = state["wrinklers"]
+            .as_array()
+            .map(|wrinklers| wrinklers.iter().filter_map(Wrinkler::from_json).collect())
+            .unwrap_or_default();
+
+        let to_pop = self.wrinklers.to_pop(&wrinklers, cookies, cps);
+
+        if to_pop.is_empty() {
+            return Ok(None);
+        }
+
+        // Wrinklers out of health are popped by the wrinkler update of the next
+        // frame, running it right away leaves the cookies they give back alone in
+        // the difference. The game pays more than they sucked, with upgrades,
+        // gods and shiny wrinklers.
+        let pop_script = r#"
+            const cookies = Game.cookies;
+            for (const id of arguments[0]) {
+                Game.wrinklers[id].hp = 0;
+            }
+            Game.UpdateWrinklers();
+            return Game.cookies - cookies;
+            "#;
+
+        let ids: Vec<Value> = to_pop
+            .iter()
+            .map(|wrinkler| Value::from(wrinkler.id))
+            .collect();
+
+        let reclaimed = driver
+            .execute(pop_script, vec![Value::from(ids)])
+            .await
+            .map_err(CookieClickerError::DriverError)?
+            .json()
+            .as_f64()
+            .unwrap_or_default();
+
+        let pop = WrinklerPop {
+            wrinklers: to_pop.len(),
+            reclaimed,
+            popped_at: Utc::now(),
+        };
+
+        self.history
+            .record_wrinkler_pop(&pop)
+            .map_err(CookieClickerError::HistoryError)?;
+
+        Ok(Some(pop))
+    }
+
+    /// Get the sugar lump state, `None` while sugar lumps are locked
+    pub async fn get_lump_state(&mut self) -> CookieClickerResult<Option<LumpState>> {
+        let driver = self.driver()?;
+
+        let state_script = r#"
+            return {
+                unlocked: Game.canLumps(),
+                lumps: Game.lumps,
+                type: Game.lumpCurrentType,
+                ripe_in: Game.lumpRipeAge - (Date.now() - Game.lumpT),
+                levels: Game.ObjectsById.map(building => building.level),
+            };
+            "#;
+
+        let state = driver
+            .execute(state_script, vec![])
+            .await
+            .map_err(CookieClickerError::DriverError)?;
+
+        Ok(LumpState::from_json(state.json()))
+    }
+
+    /// Harvest the sugar lump once ripe, then spend lumps following the lump planner
+    ///
+    /// Lumps are never harvested before they are ripe: a mature lump only has
+    /// a 50% chance to give anything, whatever its type.
+    pub async fn manage_lumps(&mut self) -> CookieClickerResult<LumpReport> {
+        let mut report = LumpReport::default();
+
+        let mut state = match self.get_lump_state().await? {
+            Some(state) => state,
+            None => return Ok(report),
+        };
+
+        let driver = self.driver()?;
+
+        if state.is_ripe() {
+            let harvest_script = r#"
+                const lumps = Game.lumps;
+                Game.clickLump();
+                return Game.lumps - lumps;
+                "#;
+
+            let harvested = driver
+                .execute(harvest_script, vec![])
+                .await
+                .map_err(CookieClickerError::DriverError)?
+                .json()
+                .as_f64()
+                .unwrap_or_default();
+
+            report.harvested = Some((state.lump_type, harvested));
+            state.lumps += harvested;
+        }
+
+        // Level up without the confirmation prompt the game may show
+        let level_up_script = r#"
+            const building = Game.ObjectsById[arguments[0]];
+            const askLumps = Game.prefs.askLumps;
+            Game.prefs.askLumps = 0;
+            building.levelUp();
+            Game.prefs.askLumps = askLumps;
+            return { level: building.level, lumps: Game.lumps };
+            "#;
+
+        while let Some(building) = self.lump_planner.next_level_up(&state.levels, state.lumps) {
+            let result = driver
+                .execute(level_up_script, vec![Value::from(building)])
+                .await
+                .map_err(CookieClickerError::DriverError)?
+                .json()
+                .clone();
+
+            let level = result["level"].as_i64().unwrap_or_default();
+
+            // The game refused, don't try again until the next run
+            if level <= state.levels[building] {
+                break;
+            }
+
+            state.levels[building] = level;
+            state.lumps = result["lumps"].as_f64().unwrap_or_default();
+            report.levels.push((building, level));
+        }
+
+        Ok(report)
+    }
+
+    /// Get the state of the garden, `None` while the Farm minigame is locked
+    pub async fn get_garden_state(&mut self) -> CookieClickerResult<Option<GardenState>> {
+        let driver = self.driver()?;
+
+        let state_script = r#"
+            const M = Game.Objects['Farm'].minigame;
+            if (!M) return null;
+            return {
+                soil: M.soil,
+                soil_ready: M.nextSoil <= Date.now(),
+                soils: M.soilsById.map(soil => ({
+                    key: soil.key,
+                    name: soil.name,
+                    unlocked: Game.Objects['Farm'].amount >= soil.req,
+                })),
+                plants: M.plantsById.map(plant => ({
+                    key: plant.key,
+                    name: plant.name,
+                    mature: plant.mature,
+                    unlocked: !!plant.unlocked,
+                })),
+                plot: M.plot.map((row, y) => row.map((tile, x) => ({
+                    unlocked: !!M.isTileUnlocked(x, y),
+                    plant: tile[0] - 1,
+                    age: tile[1],
+                }))),
+            };
+            "#;
+
+        let state = driver
+            .execute(state_script, vec![])
+            .await
+            .map_err(CookieClickerError::DriverError)?;
+
+        Ok(GardenState::from_json(state.json()))
+    }
+
+    /// Plant, harvest, switch soil and sacrifice the garden following the garden goal
+    pub async fn manage_garden(&mut self) -> CookieClickerResult<GardenReport> {
+        let mut report = GardenReport::default();
+
+        let state = match self.get_garden_state().await? {
+            Some(state) => state,
+            None => return Ok(report),
+        };
+
+        let actions = self.garden.plan(&state);
+
+        if actions.is_empty() {
+            return Ok(report);
+        }
+
+        // Mirrors what clicking the tools does, the soil buttons have no function of their own
+        let actions_script = r#"
+            const M = Game.Objects['Farm'].minigame;
+            return arguments[0].map(action => {
+                switch (action.kind) {
+                    case 'plant':
+                        return !!M.useTool(action.plant, action.x, action.y);
+                    case 'harvest':
+                        return !!M.harvest(action.x, action.y, 1);
+                    case 'soil':
+                        if (M.nextSoil > Date.now()) return false;
+                        M.nextSoil = Date.now() + (Game.Has('Turbo-charged soil') ? 1 : 1000 * 60 * 10);
+                        M.soil = action.soil;
+                        M.toCompute = true;
+                        M.computeStepT();
+                        return true;
+                    case 'sacrifice':
+                        M.convert();
+                        return true;
+                }
+                return false;
+            });
+            "#;
+
+        let performed = self
+            .driver()?
+            .execute(
+                actions_script,
+                vec![Value::from(
+                    actions
+                        .iter()
+                        .map(|action| action.to_json())
+                        .collect::<Vec<_>>(),
+                )],
+            )
+            .await
+            .map_err(CookieClickerError::DriverError)?
+            .json()
+            .clone();
+
+        for (index, action) in actions.iter().enumerate() {
+            if performed[index].as_bool().unwrap_or_default() {
+                report.add(action, &state);
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Get the state of the stock market, `None` while the Bank minigame is locked
+    pub async fn get_market_state(&mut self) -> CookieClickerResult<Option<MarketState>> {
+        let driver = self.driver()?;
+
+        let state_script = r#"
+            const M = Game.Objects['Bank'].minigame;
+            if (!M) return null;
+            return {
+                stocks: M.goodsById.filter(good => good.active).map(good => ({
+                    id: good.id,
+                    symbol: good.symbol,
+                    value: good.val,
+                    resting_value: M.getRestingVal(good.id),
+                    owned: good.stock,
+                    max_owned: M.getGoodMaxStock(good),
+                })),
+                overhead: 1 + 0.2 * Math.pow(0.95, M.brokers),
+                dollar: Game.cookiesPsRawHighest,
+                cookies: Game.cookies,
+                cps: Game.cookiesPs,
+            };
+            "#;
+
+        let state = driver
+            .execute(state_script, vec![])
+            .await
+            .map_err(CookieClickerError::DriverError)?;
+
+        Ok(MarketState::from_json(state.json()))
+    }
+
+    /// Buy and sell stocks following the trading strategy, recording each trade in the ledger
+    pub async fn trade_stocks(&mut self) -> CookieClickerResult<Vec<Trade>> {
+        let mut state = match self.get_market_state().await? {
+            Some(state) => state,
+            None => return Ok(vec![]),
+        };
+
+        let mut positions = self
+            .history
+            .market_positions()
+            .map_err(CookieClickerError::HistoryError)?;
+
+        let trade_script = r#"
+            const M = Game.Objects['Bank'].minigame;
+            const good = M.goodsById[arguments[0]];
+            const owned = good.stock;
+            if (arguments[1] === 'buy') M.buyGood(good.id, arguments[2]);
+            else M.sellGood(good.id, arguments[2]);
+            return Math.abs(good.stock - owned);
+            "#;
+
+        let mut trades = vec![];
+
+        for stock in &state.stocks.clone() {
+            let position = positions.entry(stock.symbol.clone()).or_default();
+
+            let (side, quantity) = match self.trading.decide(stock, position, &state) {
+                Some(decision) => decision,
+                None => continue,
+            };
+
+            let traded = self
+                .driver()?
+                .execute(
+                    trade_script,
+                    vec![
+                        Value::from(stock.id),
+                        Value::from(side.as_str()),
+                        Value::from(quantity),
+                    ],
+                )
+                .await
+                .map_err(CookieClickerError::DriverError)?
+                .json()
+                .as_i64()
+                .unwrap_or_default();
+
+            if traded == 0 {
+                continue;
+            }
+
+            let price = match side {
+                TradeSide::Buy => stock.value * state.overhead,
+                TradeSide::Sell => stock.value,
+            };
+
+            if side == TradeSide::Buy {
+                state.cookies -= traded as f64 * price * state.dollar;
+            }
+
+            let trade = Trade {
+                symbol: stock.symbol.clone(),
+                side,
+                quantity: traded,
+                price,
+                profit: position.apply(side, traded, price),
+                traded_at: Utc::now(),
+            };
+
+            self.history
+                .record_trade(&trade)
+                .map_err(CookieClickerError::HistoryError)?;
+
+            trades.push(trade);
+        }
+
+        Ok(trades)
+    }
+
+    /// Get the types of the active buffs
+    pub async fn get_buffs(&mut self) -> CookieClickerResult<Buffs> {
+        let driver = self.driver()?;
+
+        let buffs = driver
+            .execute(
+                "return Object.values(Game.buffs).map(buff => buff.type.name);",
+                vec![],
+            )
+            .await
+            .map_err(CookieClickerError::DriverError)?;
+
+        Ok(Buffs::from_json(buffs.json()).unwrap_or_default())
+    }
+
+    /// Get the state of the grimoire, `None` while the Wizard tower minigame is locked
+    pub async fn get_grimoire_state(&mut self) -> CookieClickerResult<Option<GrimoireState>> {
+        let driver = self.driver()?;
+
+        let state_script = r#"
+            const M = Game.Objects['Wizard tower'].minigame;
+            if (!M) return null;
+            return {
+                magic: M.magic,
+                max_magic: M.magicM,
+                hand_of_fate_cost: M.getSpellCost(M.spells['hand of fate']),
+                conjure_cost: M.getSpellCost(M.spells['conjure baked goods']),
+            };
+            "#;
+
+        let state = driver
+            .execute(state_script, vec![])
+            .await
+            .map_err(CookieClickerError::DriverError)?;
+
+        let buffs = self.get_buffs().await?;
+
+        Ok(GrimoireState::from_json(state.json(), buffs))
+    }
+
+    /// Cast a spell if the grimoire mode calls for one, recording its outcome to history
+    pub async fn cast_spells(&mut self) -> CookieClickerResult<Option<SpellCast>> {
+        let state = match self.get_grimoire_state().await? {
+            Some(state) => state,
+            None => return Ok(None),
+        };
+
+        let now = Utc::now();
+
+        let spell = match self.grimoire.choose(&state, now) {
+            Some(spell) => spell,
+            None => return Ok(None),
+        };
+
+        // Force the Hand of Fate spawns a shimmer holding the outcome, a wrath cookie when it backfires
+        let cast_script = r#"
+            const M = Game.Objects['Wizard tower'].minigame;
+            const shimmers = Game.shimmers.length;
+            const cookies = Game.cookies;
+            if (!M.castSpell(M.spells[arguments[0]])) return null;
+            const shimmer = Game.shimmers.length > shimmers ? Game.shimmers[Game.shimmers.length - 1] : null;
+            return {
+                cookies: Game.cookies - cookies,
+                backfired: shimmer ? !!shimmer.wrath : Game.cookies < cookies,
+                effect: shimmer && shimmer.force ? shimmer.force : null,
+            };
+            "#;
+
+        let outcome = self
+            .driver()?
+            .execute(cast_script, vec![Value::from(spell.key())])
+            .await
+            .map_err(CookieClickerError::DriverError)?
+            .json()
+            .clone();
+
+        if outcome.is_null() {
+            return Ok(None);
+        }
+
+        let cast = SpellCast {
+            spell,
+            backfired: outcome["backfired"].as_bool().unwrap_or_default(),
+            effect: outcome["effect"].as_str().map(str::to_string),
+            cookies: outcome["cookies"].as_f64().unwrap_or_default(),
+            cast_at: now,
+        };
+
+        if spell == Spell::ConjureBakedGoods {
+            self.grimoire.last_conjure = Some(now);
+        }
+
+        self.history
+            .record_spell_cast(&cast)
+            .map_err(CookieClickerError::HistoryError)?;
+
+        Ok(Some(cast))
+    }
+
+    /// Get the state of the pantheon, `None` while the Temple minigame is locked
+    pub async fn get_pantheon_state(&mut self) -> CookieClickerResult<Option<PantheonState>> {
+        let driver = self.driver()?;
+
+        let state_script = r#"
+            const M = Game.Objects['Temple'].minigame;
+            if (!M) return null;
+            return {
+                slots: M.slot,
+                swaps: M.swaps,
+                gods: M.godsById.map(god => ({ key: god.key, name: god.name })),
+            };
+            "#;
+
+        let state = driver
+            .execute(state_script, vec![])
+            .await
+            .map_err(CookieClickerError::DriverError)?;
+
+        let buffs = self.get_buffs().await?;
+
+        Ok(PantheonState::from_json(state.json(), buffs))
+    }
+
+    /// Slot the loadout matching the current phase, when there are enough swaps for it
+    pub async fn manage_pantheon(&mut self) -> CookieClickerResult<Option<PantheonReport>> {
+        let state = match self.get_pantheon_state().await? {
+            Some(state) => state,
+            None => return Ok(None),
+        };
+
+        let swaps = self.pantheon.plan(&state);
+
+        if swaps.is_empty() {
+            return Ok(None);
+        }
+
+        // Mirrors dropping a god on a slot, which uses a swap
+        let slot_script = r#"
+            const M = Game.Objects['Temple'].minigame;
+            const god = M.godsById[arguments[0]];
+            if (M.swaps <= 0 || god.slot == arguments[1]) return false;
+            M.useSwap(1);
+            M.lastSwapT = 0;
+            M.slotGod(god, arguments[1]);
+            return true;
+            "#;
+
+        let mut report = PantheonReport {
+            phase: state.phase(),
+            slotted: vec![],
+        };
+
+        for (slot, god) in swaps {
+            let slotted = self
+                .driver()?
+                .execute(slot_script, vec![Value::from(god), Value::from(slot)])
+                .await
+                .map_err(CookieClickerError::DriverError)?
+                .json()
+                .as_bool()
+                .unwrap_or_default();
+
+            if !slotted {
+                break;
+            }
+
+            report
+                .slotted
+                .push((slot, state.gods[god].short_name().to_string()));
+        }
+
+        Ok(Some(report).filter(|report| !report.slotted.is_empty()))
+    }
+
     /// Wait until page is loaded and the big cookie has appeared on the screen
     async fn wait_page_load(&mut self) -> CookieClickerResult<()> {
         let driver = self.driver()?;
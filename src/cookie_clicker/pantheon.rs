@@ -0,0 +1,383 @@
+use std::{env, fmt, str::FromStr};
+
+use serde_json::Value;
+
+use super::{
+    buffs::{Buffs, BUILDING_SPECIAL, CLICK_FRENZY, DRAGONFLIGHT, FRENZY},
+    settings::env_switch,
+};
+
+/// Names of the pantheon slots, by index
+const SLOT_NAMES: [&str; 3] = ["diamond", "ruby", "jade"];
+
+/// Buffs that turn a Frenzy into a combo
+const COMBO_BUFFS: [&str; 3] = [BUILDING_SPECIAL, CLICK_FRENZY, DRAGONFLIGHT];
+
+/// Key and short name of every god, as in `M.gods`
+const GODS: [(&str, &str); 11] = [
+    ("asceticism", "holobore"),
+    ("decadence", "vomitrax"),
+    ("ruin", "godzamok"),
+    ("ages", "cyclius"),
+    ("seasons", "selebrak"),
+    ("creation", "dotjeiess"),
+    ("labor", "muridal"),
+    ("industry", "jeremy"),
+    ("mother", "mokalsium"),
+    ("scorn", "skruuia"),
+    ("order", "rigidel"),
+];
+
+const DEFAULT_IDLE_LOADOUT: &str = "mother,industry,ages";
+const DEFAULT_COMBO_LOADOUT: &str = "ruin,labor,mother";
+
+/// Spirit of the Temple minigame
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct God {
+    pub key: String,
+    /// Full name, such as `Godzamok, Spirit of Ruin`
+    pub name: String,
+}
+
+impl God {
+    /// Name without the title
+    pub fn short_name(&self) -> &str {
+        self.name.split(',').next().unwrap_or(&self.name)
+    }
+
+    /// Whether a loadout entry designates this god, by key or by name
+    fn matches(&self, entry: &str) -> bool {
+        self.key.eq_ignore_ascii_case(entry) || self.short_name().eq_ignore_ascii_case(entry)
+    }
+}
+
+/// State of the Temple minigame
+#[derive(Debug, Clone, PartialEq)]
+pub struct PantheonState {
+    /// God in each slot, by id
+    pub slots: [Option<usize>; 3],
+    /// Swaps available, they come back over time
+    pub swaps: i64,
+    /// Gods, by id
+    pub gods: Vec<God>,
+    pub buffs: Buffs,
+}
+
+impl PantheonState {
+    /// Parse the state returned by the game
+    pub fn from_json(state: &Value, buffs: Buffs) -> Option<Self> {
+        let slots: Vec<Option<usize>> = state["slots"]
+            .as_array()?
+            .iter()
+            .map(|god| Some(usize::try_from(god.as_i64()?).ok()))
+            .collect::<Option<_>>()?;
+
+        Some(Self {
+            slots: slots.try_into().ok()?,
+            swaps: state["swaps"].as_i64()?,
+            gods: state["gods"]
+                .as_array()?
+                .iter()
+                .map(|god| {
+                    Some(God {
+                        key: god["key"].as_str()?.to_string(),
+                        name: god["name"].as_str()?.to_string(),
+                    })
+                })
+                .collect::<Option<_>>()?,
+            buffs,
+        })
+    }
+
+    /// Current game phase, given the active buffs
+    pub fn phase(&self) -> Phase {
+        if self.buffs.has(FRENZY) && COMBO_BUFFS.iter().any(|buff| self.buffs.has(buff)) {
+            Phase::Combo
+        } else {
+            Phase::Idle
+        }
+    }
+}
+
+impl fmt::Display for PantheonState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let slots: Vec<String> = self
+            .slots
+            .iter()
+            .zip(SLOT_NAMES)
+            .map(|(god, slot)| {
+                let god = god
+                    .and_then(|god| self.gods.get(god))
+                    .map(God::short_name)
+                    .unwrap_or("empty");
+
+                format!("{}: {}", slot, god)
+            })
+            .collect();
+
+        write!(f, "{}, {} swaps available", slots.join(", "), self.swaps)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Idle,
+    /// Frenzy stacked with a building special, a click frenzy or a dragonflight
+    Combo,
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Idle => write!(f, "idle"),
+            Self::Combo => write!(f, "combo"),
+        }
+    }
+}
+
+/// Gods to slot, by key or name, `None` leaves a slot as it is
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Loadout {
+    pub slots: [Option<String>; 3],
+}
+
+impl FromStr for Loadout {
+    type Err = ();
+
+    /// Parse up to three comma separated gods, in slot order, `-` leaving a slot as it is
+    ///
+    /// Gods are designated by key or short name, unknown gods are rejected.
+    fn from_str(loadout: &str) -> Result<Self, Self::Err> {
+        let entries: Vec<&str> = loadout.split(',').map(str::trim).collect();
+
+        if entries.len() > SLOT_NAMES.len() {
+            return Err(());
+        }
+
+        let mut slots: [Option<String>; 3] = Default::default();
+
+        for (slot, entry) in slots.iter_mut().zip(entries) {
+            if entry.is_empty() || entry == "-" {
+                continue;
+            }
+
+            let entry = entry.to_lowercase();
+
+            if !GODS
+                .iter()
+                .any(|(key, name)| entry == *key || entry == *name)
+            {
+                return Err(());
+            }
+
+            *slot = Some(entry);
+        }
+
+        Ok(Self { slots })
+    }
+}
+
+impl fmt::Display for Loadout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let slots: Vec<&str> = self
+            .slots
+            .iter()
+            .map(|god| god.as_deref().unwrap_or("-"))
+            .collect();
+
+        write!(f, "{}", slots.join(","))
+    }
+}
+
+/// Gods slotted by the pantheon task
+#[derive(Debug, Clone, PartialEq)]
+pub struct PantheonReport {
+    pub phase: Phase,
+    /// Slot and short name of each god slotted
+    pub slotted: Vec<(usize, String)>,
+}
+
+impl fmt::Display for PantheonReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let slotted: Vec<String> = self
+            .slotted
+            .iter()
+            .map(|(slot, god)| format!("{} in {}", god, SLOT_NAMES[*slot]))
+            .collect();
+
+        write!(
+            f,
+            "Switched to the {} loadout: {}",
+            self.phase,
+            slotted.join(", ")
+        )
+    }
+}
+
+/// Settings of the pantheon task
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pantheon {
+    pub enabled: bool,
+    pub idle: Loadout,
+    pub combo: Loadout,
+}
+
+impl Pantheon {
+    /// Load the settings from env `PANTHEON` (`on` or `off`), `PANTHEON_IDLE_LOADOUT` and `PANTHEON_COMBO_LOADOUT`
+    pub fn from_env() -> Self {
+        let enabled = env_switch("PANTHEON", false);
+
+        let loadout = |name: &str, default: &str| {
+            env::var(name)
+                .unwrap_or_else(|_| default.to_string())
+                .parse()
+                .unwrap_or_else(|_| panic!("Invalid env {}", name))
+        };
+
+        Self {
+            enabled,
+            idle: loadout("PANTHEON_IDLE_LOADOUT", DEFAULT_IDLE_LOADOUT),
+            combo: loadout("PANTHEON_COMBO_LOADOUT", DEFAULT_COMBO_LOADOUT),
+        }
+    }
+
+    pub fn loadout(&self, phase: Phase) -> &Loadout {
+        match phase {
+            Phase::Idle => &self.idle,
+            Phase::Combo => &self.combo,
+        }
+    }
+
+    /// Gods to slot for the current phase, as `(slot, god id)` in the order to slot them
+    ///
+    /// Each god slotted costs a swap: nothing is done until there are enough
+    /// swaps for the whole loadout, so a combo doesn't start with half of it.
+    pub fn plan(&self, state: &PantheonState) -> Vec<(usize, usize)> {
+        let mut slots = state.slots;
+        let mut swaps = vec![];
+
+        for (slot, entry) in self.loadout(state.phase()).slots.iter().enumerate() {
+            // Loadouts only contain known gods, which are all in the game
+            let god = match entry
+                .as_ref()
+                .and_then(|entry| state.gods.iter().position(|god| god.matches(entry)))
+            {
+                Some(god) => god,
+                None => continue,
+            };
+
+            if slots[slot] == Some(god) {
+                continue;
+            }
+
+            // Slotting a god already in another slot swaps the two, like the game does
+            if let Some(previous) = slots.iter().position(|slotted| *slotted == Some(god)) {
+                slots[previous] = slots[slot];
+            }
+
+            slots[slot] = Some(god);
+            swaps.push((slot, god));
+        }
+
+        if swaps.len() as i64 > state.swaps {
+            return vec![];
+        }
+
+        swaps
+    }
+}
+
+impl fmt::Display for Pantheon {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}, idle loadout {}, combo loadout {}",
+            if self.enabled { "running" } else { "paused" },
+            self.idle,
+            self.combo
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(slots: [Option<usize>; 3], swaps: i64, buffs: &[&str]) -> PantheonState {
+        let gods = [
+            ("ruin", "Godzamok, Spirit of Ruin"),
+            ("mother", "Mokalsium, Mother Spirit"),
+            ("labor", "Muridal, Spirit of Labor"),
+            ("ages", "Cyclius, Spirit of Ages"),
+        ];
+
+        PantheonState {
+            slots,
+            swaps,
+            gods: gods
+                .iter()
+                .map(|(key, name)| God {
+                    key: key.to_string(),
+                    name: name.to_string(),
+                })
+                .collect(),
+            buffs: buffs.iter().copied().collect(),
+        }
+    }
+
+    #[test]
+    fn swap_loadouts_by_phase() {
+        let pantheon = Pantheon {
+            enabled: true,
+            idle: "mother,-,ages".parse().unwrap(),
+            combo: "Godzamok,muridal,mother".parse().unwrap(),
+        };
+        let idle = [Some(1), Some(2), Some(3)];
+
+        assert_eq!(pantheon.plan(&state(idle, 3, &[])), []);
+        assert_eq!(pantheon.plan(&state(idle, 3, &["frenzy"])), []);
+
+        // Muridal is already in ruby
+        assert_eq!(
+            pantheon.plan(&state(idle, 3, &["frenzy", "click frenzy"])),
+            [(0, 0), (2, 1)]
+        );
+
+        // Not enough swaps for the whole loadout
+        assert_eq!(
+            pantheon.plan(&state(idle, 1, &["frenzy", "building buff"])),
+            []
+        );
+
+        // Back to idle, Mokalsium moves from jade to diamond and Godzamok goes to jade until Cyclius replaces it
+        assert_eq!(
+            pantheon.plan(&state([Some(0), Some(2), Some(1)], 1, &[])),
+            []
+        );
+        assert_eq!(
+            pantheon.plan(&state([Some(0), Some(2), Some(1)], 2, &[])),
+            [(0, 1), (2, 3)]
+        );
+    }
+
+    #[test]
+    fn parse_loadout() {
+        let loadout: Loadout = "Ruin, ,ages".parse().unwrap();
+
+        assert_eq!(
+            loadout.slots,
+            [Some("ruin".to_string()), None, Some("ages".to_string())]
+        );
+        assert_eq!(loadout.to_string(), "ruin,-,ages");
+        assert_eq!("ruin,labor,mother,ages".parse::<Loadout>(), Err(()));
+        assert_eq!("ruin,zeus".parse::<Loadout>(), Err(()));
+        assert_eq!(
+            "jeremy,-,Order".parse::<Loadout>().unwrap().to_string(),
+            "jeremy,-,order"
+        );
+        assert_eq!(
+            state([Some(0), None, None], 1, &[]).to_string(),
+            "diamond: Godzamok, ruby: empty, jade: empty, 1 swaps available"
+        );
+    }
+}
@@ -0,0 +1,387 @@
+use std::{collections::HashSet, env};
+
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use serde_json::{json, Value};
+
+use super::{
+    backup::{Backup, BackupError},
+    encryption::{self, DecryptionError, Encryption},
+    s3::{S3Client, S3Error},
+};
+
+/// Nanoseconds keep object dates equal to the backup dates
+const TIMESTAMP_FORMAT: &str = "%Y%m%dT%H%M%S%.9fZ";
+
+#[derive(Debug)]
+pub enum ReplicationError {
+    S3Error(S3Error),
+    BackupError(BackupError),
+    DecryptionFailed(String, DecryptionError),
+    InvalidObject(String),
+}
+
+pub type ReplicationResult<T> = Result<T, ReplicationError>;
+
+/// Off-site copy of the backups in an S3 compatible bucket
+///
+/// Each backup is uploaded once as a JSON object named after its date, so
+/// objects sort chronologically and don't depend on local ids. Pruning and
+/// later pins are not replicated, use bucket lifecycle rules to expire objects.
+/// When backups are encrypted, objects are encrypted too and bound to their name.
+#[derive(Debug)]
+pub struct Replication {
+    client: S3Client,
+    prefix: String,
+    encryption: Option<Encryption>,
+}
+
+impl Replication {
+    pub fn new(client: S3Client, prefix: &str, encryption: Option<Encryption>) -> Self {
+        Self {
+            client,
+            prefix: prefix.to_string(),
+            encryption,
+        }
+    }
+
+    /// Configure replication from env, `None` when it is disabled
+    pub fn from_env() -> Option<Self> {
+        let client = S3Client::from_env()?;
+        let prefix = env::var("S3_PREFIX").unwrap_or_else(|_| "backups/".to_string());
+
+        Some(Self::new(client, &prefix, Encryption::from_env()))
+    }
+
+    fn object_key(&self, backup: &Backup) -> String {
+        format!(
+            "{}{}.json",
+            self.prefix,
+            backup.saved_at.format(TIMESTAMP_FORMAT)
+        )
+    }
+
+    fn key_date(&self, key: &str) -> Option<DateTime<Utc>> {
+        let timestamp = key.strip_prefix(&self.prefix)?.strip_suffix(".json")?;
+        let saved_at = NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT).ok()?;
+
+        Some(Utc.from_utc_datetime(&saved_at))
+    }
+
+    async fn keys(&self) -> ReplicationResult<Vec<String>> {
+        let mut keys = self
+            .client
+            .list_objects(&self.prefix)
+            .await
+            .map_err(ReplicationError::S3Error)?;
+
+        keys.retain(|key| self.key_date(key).is_some());
+
+        Ok(keys)
+    }
+
+    /// Upload the backups missing from the bucket, returns how many were uploaded
+    ///
+    /// Objects are named after the backup dates, so this also finds backups
+    /// older than the newest replicated one, such as merged backups.
+    pub async fn upload_missing(&self, backups: &[Backup]) -> ReplicationResult<usize> {
+        let keys: HashSet<String> = self.keys().await?.into_iter().collect();
+        let mut uploaded = 0;
+
+        for backup in backups {
+            if !keys.contains(&self.object_key(backup)) {
+                self.upload(backup).await?;
+                uploaded += 1;
+            }
+        }
+
+        Ok(uploaded)
+    }
+
+    pub async fn upload(&self, backup: &Backup) -> ReplicationResult<()> {
+        let object = json!({
+            "saved_at": backup.saved_at.to_rfc3339(),
+            "save_code": backup.save_code,
+            "label": backup.label,
+            "cookies_per_second": backup.metrics.and_then(|metrics| metrics.cookies_per_second),
+        });
+        let key = self.object_key(backup);
+        let mut object = object.to_string().into_bytes();
+
+        if let Some(encryption) = &self.encryption {
+            object = encryption.encrypt(&object, key.as_bytes());
+        }
+
+        self.client
+            .put_object(&key, object)
+            .await
+            .map_err(ReplicationError::S3Error)
+    }
+
+    /// Download every replicated backup, oldest first
+    pub async fn download(&self) -> ReplicationResult<Vec<Backup>> {
+        let mut backups = vec![];
+
+        for key in self.keys().await? {
+            let object = self
+                .client
+                .get_object(&key)
+                .await
+                .map_err(ReplicationError::S3Error)?
+                .ok_or_else(|| ReplicationError::InvalidObject(key.clone()))?;
+            let object = encryption::decrypt(self.encryption.as_ref(), &object, key.as_bytes())
+                .map_err(|error| ReplicationError::DecryptionFailed(key.clone(), error))?;
+
+            let backup = parse_object(&object)
+                .ok_or_else(|| ReplicationError::InvalidObject(key.clone()))?;
+
+            backups.push(backup);
+        }
+
+        Ok(backups)
+    }
+}
+
+fn parse_object(object: &[u8]) -> Option<Backup> {
+    let object: Value = serde_json::from_slice(object).ok()?;
+
+    let saved_at = DateTime::parse_from_rfc3339(object["saved_at"].as_str()?).ok()?;
+
+    Some(Backup::restore(
+        object["save_code"].as_str()?.to_string(),
+        saved_at.with_timezone(&Utc),
+        object["label"].as_str().map(str::to_string),
+        object["cookies_per_second"].as_f64(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::BTreeMap,
+        sync::{Arc, Mutex},
+    };
+
+    use chrono::Duration;
+    use tokio::{
+        io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+        net::{TcpListener, TcpStream},
+    };
+
+    use super::super::{backup::Backups, retention::RetentionPolicy, store::MemoryStore};
+    use super::*;
+
+    type Objects = Arc<Mutex<BTreeMap<String, Vec<u8>>>>;
+
+    fn percent_decode(value: &str) -> String {
+        let mut bytes = vec![];
+        let mut rest = value.as_bytes();
+
+        while let Some((&byte, tail)) = rest.split_first() {
+            if byte == b'%' && tail.len() >= 2 {
+                let hex = std::str::from_utf8(&tail[..2]).unwrap();
+                bytes.push(u8::from_str_radix(hex, 16).unwrap());
+                rest = &tail[2..];
+            } else {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+
+        String::from_utf8(bytes).unwrap()
+    }
+
+    /// Answer a request like a bucket named `bucket` of an S3 compatible storage would
+    fn handle_request(
+        objects: &Objects,
+        method: &str,
+        target: &str,
+        signed: bool,
+        body: Vec<u8>,
+    ) -> (u16, Vec<u8>) {
+        if !signed {
+            return (403, b"<Error><Code>AccessDenied</Code></Error>".to_vec());
+        }
+
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let key = match path.strip_prefix("/bucket/") {
+            Some(key) => percent_decode(key),
+            None => return (404, b"<Error><Code>NoSuchBucket</Code></Error>".to_vec()),
+        };
+        let mut objects = objects.lock().unwrap();
+
+        match method {
+            "PUT" => {
+                objects.insert(key, body);
+                (200, vec![])
+            }
+            "GET" if key.is_empty() => {
+                let prefix = query
+                    .split('&')
+                    .find_map(|parameter| parameter.strip_prefix("prefix="))
+                    .map(percent_decode)
+                    .unwrap_or_default();
+
+                let contents: String = objects
+                    .keys()
+                    .filter(|key| key.starts_with(&prefix))
+                    .map(|key| format!("<Contents><Key>{}</Key></Contents>", key))
+                    .collect();
+
+                let xml = format!("<ListBucketResult>{}</ListBucketResult>", contents);
+
+                (200, xml.into_bytes())
+            }
+            "GET" => match objects.get(&key) {
+                Some(object) => (200, object.clone()),
+                None => (404, b"<Error><Code>NoSuchKey</Code></Error>".to_vec()),
+            },
+            _ => (405, vec![]),
+        }
+    }
+
+    async fn serve_connection(objects: Objects, stream: TcpStream) {
+        let mut stream = BufReader::new(stream);
+
+        loop {
+            let mut request_line = String::new();
+
+            if stream.read_line(&mut request_line).await.unwrap_or(0) == 0 {
+                return;
+            }
+
+            let mut parts = request_line.split_whitespace();
+            let method = parts.next().unwrap().to_string();
+            let target = parts.next().unwrap().to_string();
+
+            let mut content_length = 0;
+            let mut signed = false;
+
+            loop {
+                let mut header = String::new();
+                stream.read_line(&mut header).await.unwrap();
+
+                let header = header.trim_end();
+
+                if header.is_empty() {
+                    break;
+                }
+
+                let (name, value) = header.split_once(':').unwrap();
+
+                match name.to_lowercase().as_str() {
+                    "content-length" => content_length = value.trim().parse().unwrap(),
+                    "authorization" => signed = value.trim().starts_with("AWS4-HMAC-SHA256 "),
+                    _ => (),
+                }
+            }
+
+            let mut body = vec![0; content_length];
+            stream.read_exact(&mut body).await.unwrap();
+
+            let (status, body) = handle_request(&objects, &method, &target, signed, body);
+
+            let head = format!(
+                "HTTP/1.1 {} Stand-in\r\nContent-Length: {}\r\n\r\n",
+                status,
+                body.len()
+            );
+            stream.write_all(head.as_bytes()).await.unwrap();
+            stream.write_all(&body).await.unwrap();
+        }
+    }
+
+    /// Start a local stand-in for an S3 compatible storage, returns its endpoint
+    async fn start_stand_in(objects: Objects) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                tokio::spawn(serve_connection(objects.clone(), stream));
+            }
+        });
+
+        endpoint
+    }
+
+    fn backup(save_code: &str, age: Duration) -> Backup {
+        let mut backup = Backup::new(save_code.to_string());
+        backup.saved_at = Utc::now() - age;
+
+        backup.with_cookies_per_second(1.5)
+    }
+
+    #[tokio::test]
+    async fn replicate_and_restore() {
+        let objects = Objects::default();
+        let endpoint = start_stand_in(objects.clone()).await;
+        let client = S3Client::new(&endpoint, "bucket", "us-east-1", "key", "secret");
+        let replication = Replication::new(client, "cookies/", Encryption::new(&[1; 32], &[]));
+
+        objects
+            .lock()
+            .unwrap()
+            .insert("unrelated.txt".to_string(), vec![]);
+
+        assert!(replication.download().await.unwrap().is_empty());
+
+        let mut backups = Backups::with_store(
+            Box::new(MemoryStore::default()),
+            RetentionPolicy::parse("1d:7d").unwrap(),
+        );
+        backups.add(backup("first", Duration::hours(2))).unwrap();
+        backups.add(backup("second", Duration::hours(1))).unwrap();
+
+        let first_id = backups.list(1, 1).unwrap()[0].id.unwrap();
+        backups.pin(first_id, "start").unwrap();
+
+        let local = backups.since(None).unwrap();
+        assert_eq!(replication.upload_missing(&local).await.unwrap(), 2);
+        assert_eq!(replication.upload_missing(&local).await.unwrap(), 0);
+
+        // Save codes don't leave in plain text
+        assert!(objects
+            .lock()
+            .unwrap()
+            .values()
+            .all(|object| !object.windows(5).any(|window| window == b"first")));
+
+        let latest = local.last().unwrap().saved_at;
+        backups.add(backup("third", Duration::zero())).unwrap();
+        let pending = backups.since(Some(latest)).unwrap();
+        assert_eq!(pending.len(), 1);
+        replication.upload(&pending[0]).await.unwrap();
+
+        // Merged backups are older than the replicated ones
+        backups
+            .merge(vec![backup("merged", Duration::minutes(90))])
+            .unwrap();
+        let merged = backups.take_merged().unwrap();
+        assert_eq!(replication.upload_missing(&merged).await.unwrap(), 1);
+
+        let mut restored = Backups::with_store(
+            Box::new(MemoryStore::default()),
+            RetentionPolicy::parse("1d:7d").unwrap(),
+        );
+
+        for backup in replication.download().await.unwrap() {
+            assert!(restored.import(backup).unwrap());
+        }
+
+        let save_codes: Vec<String> = restored
+            .since(None)
+            .unwrap()
+            .into_iter()
+            .map(|backup| backup.save_code)
+            .collect();
+        assert_eq!(save_codes, ["first", "merged", "second", "third"]);
+
+        let pinned = restored.get_by_label("start").unwrap().unwrap();
+        assert_eq!(pinned.save_code, "first");
+        assert_eq!(
+            restored.latest_backup().unwrap().unwrap().saved_at,
+            backups.latest_backup().unwrap().unwrap().saved_at
+        );
+    }
+}
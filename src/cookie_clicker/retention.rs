@@ -0,0 +1,228 @@
+use std::{collections::HashSet, env, fmt};
+
+use chrono::{DateTime, Duration, Utc};
+
+/// Keep every minute for an hour, hourly for a week and daily for a year
+const DEFAULT_RETENTION: &str = "1m:1h,1h:7d,1d:365d";
+
+/// Keep one backup every `interval` for backups younger than `keep_for`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetentionTier {
+    pub interval: Duration,
+    pub keep_for: Duration,
+}
+
+/// Grandfather-father-son retention policy for backups
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    tiers: Vec<RetentionTier>,
+}
+
+impl RetentionPolicy {
+    /// Load the policy from env `BACKUP_RETENTION`, in the `interval:keep_for,...` format
+    ///
+    /// The default policy is used when the variable is not set or empty.
+    pub fn from_env() -> Self {
+        let policy = env::var("BACKUP_RETENTION")
+            .ok()
+            .filter(|policy| !policy.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_RETENTION.to_string());
+
+        Self::parse(&policy).expect("Invalid env BACKUP_RETENTION")
+    }
+
+    /// Parse a policy such as `1m:1h,1h:7d,1d:365d`
+    pub fn parse(policy: &str) -> Option<Self> {
+        let mut tiers = policy
+            .split(',')
+            .map(|tier| {
+                let (interval, keep_for) = tier.trim().split_once(':')?;
+
+                Some(RetentionTier {
+                    interval: parse_duration(interval)?,
+                    keep_for: parse_duration(keep_for)?,
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        tiers.sort_by_key(|tier| tier.keep_for);
+
+        Some(Self { tiers })
+    }
+
+    pub fn tiers(&self) -> &[RetentionTier] {
+        &self.tiers
+    }
+
+    /// Select the ids of the backups that should be deleted
+    ///
+    /// In each tier only the oldest backup of every interval is kept, so that
+    /// kept backups stay stable as they age. The newest backup is never expired.
+    pub fn expired(&self, backups: &[(i64, DateTime<Utc>)], now: DateTime<Utc>) -> Vec<i64> {
+        let newest = backups.iter().max_by_key(|(_, saved_at)| *saved_at);
+
+        let mut backups = backups.to_vec();
+        backups.sort_by_key(|(id, saved_at)| (*saved_at, *id));
+
+        let mut buckets = HashSet::new();
+
+        backups
+            .into_iter()
+            .filter(|backup| Some(backup) != newest)
+            .filter_map(|(id, saved_at)| {
+                let age = now - saved_at;
+                let tier = self.tiers.iter().position(|tier| age <= tier.keep_for);
+
+                let keep = match tier {
+                    Some(tier) => {
+                        let interval = self.tiers[tier].interval.num_seconds().max(1);
+                        let bucket = saved_at.timestamp().div_euclid(interval);
+
+                        buckets.insert((tier, bucket))
+                    }
+                    None => false,
+                };
+
+                if keep {
+                    None
+                } else {
+                    Some(id)
+                }
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for RetentionPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let tiers: Vec<String> = self
+            .tiers
+            .iter()
+            .map(|tier| {
+                format!(
+                    "one every {} for {}",
+                    format_duration(tier.interval),
+                    format_duration(tier.keep_for)
+                )
+            })
+            .collect();
+
+        write!(f, "{}", tiers.join(", "))
+    }
+}
+
+const DURATION_UNITS: [(char, i64); 5] = [
+    ('w', 7 * 24 * 60 * 60),
+    ('d', 24 * 60 * 60),
+    ('h', 60 * 60),
+    ('m', 60),
+    ('s', 1),
+];
+
+/// Parse a duration such as `90s`, `1h` or `365d`
+fn parse_duration(duration: &str) -> Option<Duration> {
+    let unit = duration.chars().last()?;
+    let amount: i64 = duration[..duration.len() - unit.len_utf8()].parse().ok()?;
+    let (_, seconds) = DURATION_UNITS.iter().find(|(name, _)| *name == unit)?;
+
+    if amount <= 0 {
+        return None;
+    }
+
+    let seconds = amount.checked_mul(*seconds)?;
+
+    Duration::from_std(std::time::Duration::from_secs(seconds as u64)).ok()
+}
+
+/// Format a duration with the largest unit that divides it
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.num_seconds();
+    let (unit, unit_seconds) = DURATION_UNITS
+        .iter()
+        .find(|(_, unit_seconds)| seconds % unit_seconds == 0)
+        .unwrap_or(&('s', 1));
+
+    format!("{}{}", seconds / unit_seconds, unit)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn parse_policies() {
+        let policy = RetentionPolicy::parse("1d:365d, 1m:1h").unwrap();
+
+        assert_eq!(
+            policy.tiers(),
+            [
+                RetentionTier {
+                    interval: Duration::minutes(1),
+                    keep_for: Duration::hours(1),
+                },
+                RetentionTier {
+                    interval: Duration::days(1),
+                    keep_for: Duration::days(365),
+                },
+            ]
+        );
+        assert_eq!(
+            policy.to_string(),
+            "one every 1m for 1h, one every 1d for 365d"
+        );
+
+        for malformed in [
+            "",
+            "1m",
+            "1m:1h,",
+            "1x:1h",
+            "m:1h",
+            "0m:1h",
+            "-1m:1h",
+            "1.5h:1d",
+            "99999999999999999w:1d",
+        ] {
+            assert_eq!(RetentionPolicy::parse(malformed), None, "{}", malformed);
+        }
+    }
+
+    #[test]
+    fn format_durations() {
+        assert_eq!(format_duration(Duration::seconds(90)), "90s");
+        assert_eq!(format_duration(Duration::minutes(90)), "90m");
+        assert_eq!(format_duration(Duration::hours(36)), "36h");
+        assert_eq!(format_duration(Duration::days(14)), "2w");
+        assert_eq!(format_duration(Duration::days(365)), "365d");
+    }
+
+    #[test]
+    fn expire_backups_by_tier() {
+        let policy = RetentionPolicy::parse("1m:1h,1h:1d").unwrap();
+        // On the hour, so that intervals start right before it
+        let now = Utc.timestamp_opt(1_699_999_200, 0).unwrap();
+        let backup = |id, age: Duration| (id, now - age);
+
+        let backups = [
+            backup(2, Duration::seconds(30)),
+            backup(1, Duration::zero()),
+            backup(3, Duration::seconds(50)),
+            backup(4, Duration::hours(1)),
+            backup(5, Duration::hours(1) + Duration::seconds(1)),
+            backup(6, Duration::minutes(90)),
+            backup(7, Duration::days(1)),
+            backup(8, Duration::days(1) + Duration::seconds(1)),
+        ];
+
+        // The oldest backup of each interval is kept, backups at the end of a
+        // tier still belong to it, and older ones to the next tier or none
+        assert_eq!(policy.expired(&backups, now), [8, 5, 2]);
+
+        // The newest backup is kept even when it is older than every tier
+        assert_eq!(
+            policy.expired(&[backup(1, Duration::days(2))], now),
+            Vec::<i64>::new()
+        );
+    }
+}
@@ -0,0 +1,975 @@
+use std::{fmt, string::FromUtf8Error};
+
+const END_MARKER: &str = "!END!";
+const ESCAPED_END_MARKER: &str = "%21END%21";
+const MIN_SUPPORTED_VERSION: f64 = 2.0;
+
+#[derive(Debug)]
+pub enum SaveError {
+    Empty,
+    MissingEndMarker,
+    InvalidEscape,
+    Truncated(&'static str),
+    Base64Error(base64::DecodeError),
+    Utf8Error(FromUtf8Error),
+    UnsupportedVersion(String),
+    MissingSection(&'static str),
+    MissingField {
+        section: &'static str,
+        field: &'static str,
+    },
+    InvalidField {
+        section: &'static str,
+        field: &'static str,
+        value: String,
+    },
+}
+
+pub type SaveResult<T> = Result<T, SaveError>;
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::Empty => write!(f, "the save code is empty"),
+            SaveError::MissingEndMarker => write!(
+                f,
+                "the save code does not end with {}, it is probably truncated",
+                END_MARKER
+            ),
+            SaveError::InvalidEscape => {
+                write!(f, "the save code contains an invalid URL escape sequence")
+            }
+            SaveError::Truncated(section) => write!(f, "the {} section is truncated", section),
+            SaveError::Base64Error(error) => {
+                write!(f, "the save code is not valid base64: {}", error)
+            }
+            SaveError::Utf8Error(_) => write!(f, "the decoded save is not valid UTF-8"),
+            SaveError::UnsupportedVersion(version) => write!(
+                f,
+                "game version {} is not supported, at least {} is required",
+                version, MIN_SUPPORTED_VERSION
+            ),
+            SaveError::MissingSection(section) => write!(
+                f,
+                "the {} section is missing, the save is probably truncated",
+                section
+            ),
+            SaveError::MissingField { section, field } => {
+                write!(f, "the {} section has no {} field", section, field)
+            }
+            SaveError::InvalidField {
+                section,
+                field,
+                value,
+            } => write!(
+                f,
+                "invalid value {:?} for {} in the {} section",
+                value, field, section
+            ),
+        }
+    }
+}
+
+/// Outer representation of a save code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaveFormat {
+    /// As given by the "Export save" option of the game
+    Exported,
+    /// URL-escaped, as stored by the game in local storage
+    Escaped,
+}
+
+/// Details about the current run
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunDetails {
+    /// Start of the current ascension, in milliseconds since the epoch
+    pub start_date: i64,
+    /// Start of the legacy, in milliseconds since the epoch
+    pub full_date: i64,
+    /// Last time the game was opened, in milliseconds since the epoch
+    pub last_date: i64,
+    pub bakery_name: String,
+    pub seed: String,
+    /// Fields added by newer versions of the game
+    pub extra: Vec<String>,
+}
+
+/// Counters and global game state
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stats {
+    pub cookies: f64,
+    pub cookies_earned: f64,
+    pub cookie_clicks: i64,
+    pub golden_clicks: i64,
+    pub handmade_cookies: f64,
+    pub missed_golden_clicks: i64,
+    pub background_type: i64,
+    pub milk_type: i64,
+    /// Cookies baked in previous ascensions
+    pub cookies_reset: f64,
+    pub elder_wrath: i64,
+    pub pledges: i64,
+    pub pledge_time: i64,
+    pub next_research: i64,
+    pub research_time: i64,
+    /// Number of ascensions
+    pub resets: i64,
+    pub golden_clicks_local: i64,
+    pub cookies_sucked: f64,
+    pub wrinklers_popped: i64,
+    pub santa_level: i64,
+    pub reindeer_clicked: i64,
+    pub season_time: i64,
+    pub season_uses: i64,
+    pub season: String,
+    /// Cookies currently sucked by wrinklers
+    pub wrinkler_cookies: f64,
+    pub wrinklers: i64,
+    /// Prestige level
+    pub prestige: f64,
+    pub heavenly_chips: f64,
+    pub heavenly_chips_spent: f64,
+    pub heavenly_cookies: f64,
+    pub ascension_mode: i64,
+    pub permanent_upgrades: [i64; 5],
+    pub dragon_level: i64,
+    pub dragon_aura: i64,
+    pub dragon_aura_2: i64,
+    pub chime_type: i64,
+    pub volume: i64,
+    pub shiny_wrinklers: i64,
+    pub shiny_wrinkler_cookies: f64,
+    /// Sugar lumps in the bank, `-1` until they are unlocked
+    pub lumps: f64,
+    pub lumps_total: f64,
+    /// When the current sugar lump started growing, in milliseconds since the epoch
+    pub lump_time: f64,
+    pub lump_refill: f64,
+    pub lump_current_type: i64,
+    /// Fields added by newer versions of the game
+    pub extra: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Building {
+    pub amount: i64,
+    pub bought: i64,
+    pub total_cookies: f64,
+    pub level: i64,
+    /// Opaque minigame state, empty for buildings without a minigame
+    pub minigame: String,
+    /// Fields added by newer versions of the game
+    pub extra: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Upgrade {
+    pub unlocked: bool,
+    pub bought: bool,
+}
+
+/// Decoded Cookie Clicker save
+#[derive(Debug, Clone, PartialEq)]
+pub struct SaveData {
+    pub format: SaveFormat,
+    pub version: f64,
+    /// Unused by the game, kept for lossless encoding
+    pub reserved: String,
+    pub run: RunDetails,
+    /// Packed game settings
+    pub preferences: String,
+    pub stats: Stats,
+    /// Buildings, ordered by id
+    pub buildings: Vec<Building>,
+    /// Upgrades, ordered by id
+    pub upgrades: Vec<Upgrade>,
+    /// Whether each achievement is won, ordered by id
+    pub achievements: Vec<bool>,
+    /// Packed active buffs
+    pub buffs: String,
+    /// Sections added by newer versions of the game
+    pub extra: Vec<String>,
+}
+
+impl SaveData {
+    /// Decode a save code in either the exported or the escaped format
+    pub fn decode(save_code: &str) -> SaveResult<Self> {
+        let (format, payload) = unwrap_save_code(save_code)?;
+        let decoded = String::from_utf8(payload).map_err(SaveError::Utf8Error)?;
+
+        Self::parse(format, &decoded)
+    }
+
+    /// Parse the decoded contents of a save
+    fn parse(format: SaveFormat, contents: &str) -> SaveResult<Self> {
+        let mut sections = contents.split('|');
+        let mut next_section =
+            |name: &'static str| sections.next().ok_or(SaveError::MissingSection(name));
+
+        let version_field = next_section("version")?;
+        let version: f64 = version_field
+            .parse()
+            .map_err(|_| SaveError::UnsupportedVersion(version_field.to_string()))?;
+
+        if version.is_nan() || version < MIN_SUPPORTED_VERSION {
+            return Err(SaveError::UnsupportedVersion(version_field.to_string()));
+        }
+
+        let reserved = next_section("reserved")?.to_string();
+        let run = parse_run(next_section("run")?)?;
+        let preferences = next_section("preferences")?.to_string();
+        let stats = parse_stats(next_section("stats")?)?;
+        let buildings = parse_buildings(next_section("buildings")?)?;
+        let upgrades = parse_upgrades(next_section("upgrades")?)?;
+        let achievements = parse_achievements(next_section("achievements")?)?;
+        let buffs = next_section("buffs")?.to_string();
+        let extra = sections.map(str::to_string).collect();
+
+        Ok(Self {
+            format,
+            version,
+            reserved,
+            run,
+            preferences,
+            stats,
+            buildings,
+            upgrades,
+            achievements,
+            buffs,
+            extra,
+        })
+    }
+
+    /// Encode the save, in the same format it was decoded from
+    pub fn encode(&self) -> String {
+        wrap_save_code(self.format, self.contents().as_bytes())
+    }
+
+    /// Serialize the decoded contents of the save, as written by the game
+    fn contents(&self) -> String {
+        let mut sections = vec![
+            js_number(self.version),
+            self.reserved.clone(),
+            write_run(&self.run),
+            self.preferences.clone(),
+            write_stats(&self.stats),
+            write_buildings(&self.buildings),
+            write_upgrades(&self.upgrades),
+            write_achievements(&self.achievements),
+            self.buffs.clone(),
+        ];
+        sections.extend(self.extra.iter().cloned());
+
+        sections.join("|")
+    }
+}
+
+/// Strip the outer encoding of a save code, returning its format and decoded payload
+pub(super) fn unwrap_save_code(save_code: &str) -> SaveResult<(SaveFormat, Vec<u8>)> {
+    let (format, save_code) = if save_code.contains(ESCAPED_END_MARKER) {
+        (SaveFormat::Escaped, unescape(save_code)?)
+    } else {
+        (SaveFormat::Exported, save_code.to_string())
+    };
+
+    let encoded = save_code
+        .strip_suffix(END_MARKER)
+        .ok_or(SaveError::MissingEndMarker)?;
+
+    let payload = base64::decode(encoded).map_err(SaveError::Base64Error)?;
+
+    Ok((format, payload))
+}
+
+/// Inverse of `unwrap_save_code`
+pub(super) fn wrap_save_code(format: SaveFormat, payload: &[u8]) -> String {
+    let save_code = base64::encode(payload) + END_MARKER;
+
+    match format {
+        SaveFormat::Exported => save_code,
+        SaveFormat::Escaped => escape(&save_code),
+    }
+}
+
+/// Clean up a pasted save code and make sure it can be decoded
+///
+/// Whitespace is stripped and URL-escaped codes are unescaped, so the result is
+/// always in the exported format.
+pub fn normalize_save_code(save_code: &str) -> SaveResult<String> {
+    let save_code: String = save_code.chars().filter(|c| !c.is_whitespace()).collect();
+
+    if save_code.is_empty() {
+        return Err(SaveError::Empty);
+    }
+
+    let save_code = if save_code.contains('%') {
+        unescape(&save_code)?
+    } else {
+        save_code
+    };
+
+    SaveData::decode(&save_code)?;
+
+    Ok(save_code)
+}
+
+/// Cursor over the separated fields of a section
+struct Fields<'a> {
+    section: &'static str,
+    fields: std::vec::IntoIter<&'a str>,
+}
+
+impl<'a> Fields<'a> {
+    fn new(section: &'static str, fields: Vec<&'a str>) -> Self {
+        Self {
+            section,
+            fields: fields.into_iter(),
+        }
+    }
+
+    fn string(&mut self, field: &'static str) -> SaveResult<String> {
+        self.fields
+            .next()
+            .map(str::to_string)
+            .ok_or(SaveError::MissingField {
+                section: self.section,
+                field,
+            })
+    }
+
+    fn int(&mut self, field: &'static str) -> SaveResult<i64> {
+        let value = self.string(field)?;
+
+        value.parse().map_err(|_| SaveError::InvalidField {
+            section: self.section,
+            field,
+            value,
+        })
+    }
+
+    fn float(&mut self, field: &'static str) -> SaveResult<f64> {
+        let value = self.string(field)?;
+
+        value.parse().map_err(|_| SaveError::InvalidField {
+            section: self.section,
+            field,
+            value,
+        })
+    }
+
+    fn rest(self) -> Vec<String> {
+        self.fields.map(str::to_string).collect()
+    }
+}
+
+/// Split a section whose fields are each followed by `terminator`
+fn split_terminated<'a>(
+    section: &'static str,
+    contents: &'a str,
+    terminator: char,
+) -> SaveResult<Vec<&'a str>> {
+    if contents.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let contents = contents
+        .strip_suffix(terminator)
+        .ok_or(SaveError::Truncated(section))?;
+
+    Ok(contents.split(terminator).collect())
+}
+
+fn parse_run(contents: &str) -> SaveResult<RunDetails> {
+    let mut fields = Fields::new("run", contents.split(';').collect());
+
+    Ok(RunDetails {
+        start_date: fields.int("start_date")?,
+        full_date: fields.int("full_date")?,
+        last_date: fields.int("last_date")?,
+        bakery_name: fields.string("bakery_name")?,
+        seed: fields.string("seed")?,
+        extra: fields.rest(),
+    })
+}
+
+fn parse_stats(contents: &str) -> SaveResult<Stats> {
+    let mut fields = Fields::new("stats", split_terminated("stats", contents, ';')?);
+
+    Ok(Stats {
+        cookies: fields.float("cookies")?,
+        cookies_earned: fields.float("cookies_earned")?,
+        cookie_clicks: fields.int("cookie_clicks")?,
+        golden_clicks: fields.int("golden_clicks")?,
+        handmade_cookies: fields.float("handmade_cookies")?,
+        missed_golden_clicks: fields.int("missed_golden_clicks")?,
+        background_type: fields.int("background_type")?,
+        milk_type: fields.int("milk_type")?,
+        cookies_reset: fields.float("cookies_reset")?,
+        elder_wrath: fields.int("elder_wrath")?,
+        pledges: fields.int("pledges")?,
+        pledge_time: fields.int("pledge_time")?,
+        next_research: fields.int("next_research")?,
+        research_time: fields.int("research_time")?,
+        resets: fields.int("resets")?,
+        golden_clicks_local: fields.int("golden_clicks_local")?,
+        cookies_sucked: fields.float("cookies_sucked")?,
+        wrinklers_popped: fields.int("wrinklers_popped")?,
+        santa_level: fields.int("santa_level")?,
+        reindeer_clicked: fields.int("reindeer_clicked")?,
+        season_time: fields.int("season_time")?,
+        season_uses: fields.int("season_uses")?,
+        season: fields.string("season")?,
+        wrinkler_cookies: fields.float("wrinkler_cookies")?,
+        wrinklers: fields.int("wrinklers")?,
+        prestige: fields.float("prestige")?,
+        heavenly_chips: fields.float("heavenly_chips")?,
+        heavenly_chips_spent: fields.float("heavenly_chips_spent")?,
+        heavenly_cookies: fields.float("heavenly_cookies")?,
+        ascension_mode: fields.int("ascension_mode")?,
+        permanent_upgrades: [
+            fields.int("permanent_upgrades")?,
+            fields.int("permanent_upgrades")?,
+            fields.int("permanent_upgrades")?,
+            fields.int("permanent_upgrades")?,
+            fields.int("permanent_upgrades")?,
+        ],
+        dragon_level: fields.int("dragon_level")?,
+        dragon_aura: fields.int("dragon_aura")?,
+        dragon_aura_2: fields.int("dragon_aura_2")?,
+        chime_type: fields.int("chime_type")?,
+        volume: fields.int("volume")?,
+        shiny_wrinklers: fields.int("shiny_wrinklers")?,
+        shiny_wrinkler_cookies: fields.float("shiny_wrinkler_cookies")?,
+        lumps: fields.float("lumps")?,
+        lumps_total: fields.float("lumps_total")?,
+        lump_time: fields.float("lump_time")?,
+        lump_refill: fields.float("lump_refill")?,
+        lump_current_type: fields.int("lump_current_type")?,
+        extra: fields.rest(),
+    })
+}
+
+fn parse_buildings(contents: &str) -> SaveResult<Vec<Building>> {
+    split_terminated("buildings", contents, ';')?
+        .into_iter()
+        .map(|building| {
+            let mut fields = Fields::new("buildings", building.split(',').collect());
+
+            Ok(Building {
+                amount: fields.int("amount")?,
+                bought: fields.int("bought")?,
+                total_cookies: fields.float("total_cookies")?,
+                level: fields.int("level")?,
+                minigame: fields.string("minigame")?,
+                extra: fields.rest(),
+            })
+        })
+        .collect()
+}
+
+fn parse_flag(section: &'static str, field: &'static str, flag: char) -> SaveResult<bool> {
+    match flag {
+        '0' => Ok(false),
+        '1' => Ok(true),
+        flag => Err(SaveError::InvalidField {
+            section,
+            field,
+            value: flag.to_string(),
+        }),
+    }
+}
+
+fn parse_upgrades(contents: &str) -> SaveResult<Vec<Upgrade>> {
+    let flags: Vec<char> = contents.chars().collect();
+
+    flags
+        .chunks(2)
+        .map(|flags| match flags {
+            [unlocked, bought] => Ok(Upgrade {
+                unlocked: parse_flag("upgrades", "unlocked", *unlocked)?,
+                bought: parse_flag("upgrades", "bought", *bought)?,
+            }),
+            _ => Err(SaveError::MissingField {
+                section: "upgrades",
+                field: "bought",
+            }),
+        })
+        .collect()
+}
+
+fn parse_achievements(contents: &str) -> SaveResult<Vec<bool>> {
+    contents
+        .chars()
+        .map(|won| parse_flag("achievements", "won", won))
+        .collect()
+}
+
+/// Reverse JavaScript's `escape`
+fn unescape(escaped: &str) -> SaveResult<String> {
+    let mut unescaped = String::with_capacity(escaped.len());
+    let mut chars = escaped.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            unescaped.push(c);
+            continue;
+        }
+
+        let high = chars.next().and_then(|c| c.to_digit(16));
+        let low = chars.next().and_then(|c| c.to_digit(16));
+
+        match (high, low) {
+            (Some(high), Some(low)) => unescaped.push(char::from((high * 16 + low) as u8)),
+            _ => return Err(SaveError::InvalidEscape),
+        }
+    }
+
+    Ok(unescaped)
+}
+
+fn write_run(run: &RunDetails) -> String {
+    let mut fields = vec![
+        run.start_date.to_string(),
+        run.full_date.to_string(),
+        run.last_date.to_string(),
+        run.bakery_name.clone(),
+        run.seed.clone(),
+    ];
+    fields.extend(run.extra.iter().cloned());
+
+    fields.join(";")
+}
+
+fn write_stats(stats: &Stats) -> String {
+    let mut fields = vec![
+        js_number(stats.cookies),
+        js_number(stats.cookies_earned),
+        stats.cookie_clicks.to_string(),
+        stats.golden_clicks.to_string(),
+        js_number(stats.handmade_cookies),
+        stats.missed_golden_clicks.to_string(),
+        stats.background_type.to_string(),
+        stats.milk_type.to_string(),
+        js_number(stats.cookies_reset),
+        stats.elder_wrath.to_string(),
+        stats.pledges.to_string(),
+        stats.pledge_time.to_string(),
+        stats.next_research.to_string(),
+        stats.research_time.to_string(),
+        stats.resets.to_string(),
+        stats.golden_clicks_local.to_string(),
+        js_number(stats.cookies_sucked),
+        stats.wrinklers_popped.to_string(),
+        stats.santa_level.to_string(),
+        stats.reindeer_clicked.to_string(),
+        stats.season_time.to_string(),
+        stats.season_uses.to_string(),
+        stats.season.clone(),
+        js_number(stats.wrinkler_cookies),
+        stats.wrinklers.to_string(),
+        js_number(stats.prestige),
+        js_number(stats.heavenly_chips),
+        js_number(stats.heavenly_chips_spent),
+        js_number(stats.heavenly_cookies),
+        stats.ascension_mode.to_string(),
+    ];
+    fields.extend(stats.permanent_upgrades.iter().map(i64::to_string));
+    fields.extend([
+        stats.dragon_level.to_string(),
+        stats.dragon_aura.to_string(),
+        stats.dragon_aura_2.to_string(),
+        stats.chime_type.to_string(),
+        stats.volume.to_string(),
+        stats.shiny_wrinklers.to_string(),
+        js_number(stats.shiny_wrinkler_cookies),
+        js_number(stats.lumps),
+        js_number(stats.lumps_total),
+        js_number(stats.lump_time),
+        js_number(stats.lump_refill),
+        stats.lump_current_type.to_string(),
+    ]);
+    fields.extend(stats.extra.iter().cloned());
+
+    fields.into_iter().map(|field| field + ";").collect()
+}
+
+fn write_buildings(buildings: &[Building]) -> String {
+    buildings
+        .iter()
+        .map(|building| {
+            let mut fields = vec![
+                building.amount.to_string(),
+                building.bought.to_string(),
+                js_number(building.total_cookies),
+                building.level.to_string(),
+                building.minigame.clone(),
+            ];
+            fields.extend(building.extra.iter().cloned());
+
+            fields.join(",") + ";"
+        })
+        .collect()
+}
+
+fn write_flag(flag: bool) -> char {
+    if flag {
+        '1'
+    } else {
+        '0'
+    }
+}
+
+fn write_upgrades(upgrades: &[Upgrade]) -> String {
+    upgrades
+        .iter()
+        .flat_map(|upgrade| [write_flag(upgrade.unlocked), write_flag(upgrade.bought)])
+        .collect()
+}
+
+fn write_achievements(achievements: &[bool]) -> String {
+    achievements.iter().copied().map(write_flag).collect()
+}
+
+/// Format a number the way JavaScript's `Number.prototype.toString` does
+fn js_number(number: f64) -> String {
+    if number.is_nan() {
+        return "NaN".to_string();
+    }
+
+    if number.is_infinite() {
+        let sign = if number < 0.0 { "-" } else { "" };
+        return format!("{}Infinity", sign);
+    }
+
+    if number == 0.0 {
+        return "0".to_string();
+    }
+
+    // Shortest round-trip digits, the same ones JavaScript picks
+    let scientific = format!("{:e}", number.abs());
+    let (mantissa, exponent) = scientific
+        .split_once('e')
+        .expect("Scientific notation always has an exponent");
+    let digits = mantissa.replace('.', "");
+    let digits_count = digits.len() as i32;
+    let point = exponent
+        .parse::<i32>()
+        .expect("Scientific notation always has an integer exponent")
+        + 1;
+
+    let formatted = if digits_count <= point && point <= 21 {
+        digits + &"0".repeat((point - digits_count) as usize)
+    } else if 0 < point && point <= 21 {
+        let (integer, fraction) = digits.split_at(point as usize);
+        format!("{}.{}", integer, fraction)
+    } else if -6 < point && point <= 0 {
+        format!("0.{}{}", "0".repeat(-point as usize), digits)
+    } else {
+        let exponent = point - 1;
+        let sign = if exponent < 0 { '-' } else { '+' };
+        let (first, rest) = digits.split_at(1);
+        let mantissa = if rest.is_empty() {
+            first.to_string()
+        } else {
+            format!("{}.{}", first, rest)
+        };
+
+        format!("{}e{}{}", mantissa, sign, exponent.abs())
+    };
+
+    if number < 0.0 {
+        format!("-{}", formatted)
+    } else {
+        formatted
+    }
+}
+
+const NUMBER_NAMES: [&str; 20] = [
+    "million",
+    "billion",
+    "trillion",
+    "quadrillion",
+    "quintillion",
+    "sextillion",
+    "septillion",
+    "octillion",
+    "nonillion",
+    "decillion",
+    "undecillion",
+    "duodecillion",
+    "tredecillion",
+    "quattuordecillion",
+    "quindecillion",
+    "sexdecillion",
+    "septendecillion",
+    "octodecillion",
+    "novemdecillion",
+    "vigintillion",
+];
+
+/// Building names, ordered by id
+const BUILDING_NAMES: [&str; 20] = [
+    "Cursor",
+    "Grandma",
+    "Farm",
+    "Mine",
+    "Factory",
+    "Bank",
+    "Temple",
+    "Wizard tower",
+    "Shipment",
+    "Alchemy lab",
+    "Portal",
+    "Time machine",
+    "Antimatter condenser",
+    "Prism",
+    "Chancemaker",
+    "Fractal engine",
+    "Javascript console",
+    "Idleverse",
+    "Cortex baker",
+    "You",
+];
+
+/// Name of a building, falling back to its id for buildings added by newer versions
+pub fn building_name(id: usize) -> String {
+    match BUILDING_NAMES.get(id) {
+        Some(name) => name.to_string(),
+        None => format!("Building #{}", id),
+    }
+}
+
+/// Id of a building from its name, ignoring case
+pub fn building_id(name: &str) -> Option<usize> {
+    BUILDING_NAMES
+        .iter()
+        .position(|building| building.eq_ignore_ascii_case(name.trim()))
+}
+
+/// Format a cookie count with long number names, like the game does
+pub fn beautify(number: f64) -> String {
+    if !number.is_finite() {
+        return js_number(number);
+    }
+
+    let sign = if number < 0.0 { "-" } else { "" };
+    let number = number.abs();
+
+    if number < 1e6 {
+        let digits = format!("{:.0}", number.floor());
+        let grouped: Vec<&str> = digits
+            .as_bytes()
+            .rchunks(3)
+            .rev()
+            .map(|group| std::str::from_utf8(group).expect("Digits are ASCII"))
+            .collect();
+
+        return format!("{}{}", sign, grouped.join(","));
+    }
+
+    let mut thousands = (number.log10() / 3.0).floor() as usize;
+    let mut mantissa = number / 10f64.powi(3 * thousands as i32);
+
+    // A mantissa rounding up to 1000 is shown with the next name
+    if (mantissa * 1000.0).round() >= 1e6 {
+        thousands += 1;
+        mantissa /= 1000.0;
+    }
+
+    match NUMBER_NAMES.get(thousands - 2) {
+        Some(name) => format!("{}{:.3} {}", sign, mantissa, name),
+        None => format!("{}{:.3e}", sign, number),
+    }
+}
+
+/// Same as JavaScript's `escape`, for ASCII input
+fn escape(unescaped: &str) -> String {
+    unescaped
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || "@*_+-./".contains(c) {
+                c.to_string()
+            } else {
+                format!("%{:02X}", c as u32)
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Hand-built saves following the layout written by each version of the game
+    ///
+    /// They share the same start date, seed and minigame data, they are not
+    /// genuine exports: they only prove that the layouts of these versions
+    /// round-trip, not that every save of these versions does. Real saves are
+    /// checked by `saves_db_round_trip`.
+    const CORPUS: [(&str, &str); 4] = [
+        ("2.022", include_str!("./test_saves/v2.022.txt")),
+        ("2.031", include_str!("./test_saves/v2.031.txt")),
+        ("2.048", include_str!("./test_saves/v2.048.txt")),
+        ("2.052", include_str!("./test_saves/v2.052.txt")),
+    ];
+
+    #[test]
+    fn corpus_round_trip() {
+        for (version, save_code) in CORPUS {
+            let save_code = save_code.trim_end();
+            let save = SaveData::decode(save_code).expect(version);
+
+            assert_eq!(js_number(save.version), version);
+            assert_eq!(save.encode(), save_code, "{}", version);
+        }
+    }
+
+    /// Round-trip every backup of a real database, run with
+    /// `SAVES_DB=/path/to/saves.db cargo test -- --ignored saves_db_round_trip`
+    ///
+    /// The database is copied first, as opening it migrates it.
+    #[test]
+    #[ignore]
+    fn saves_db_round_trip() {
+        use super::super::{
+            encryption::Encryption,
+            store::{BackupStore, SqliteStore},
+        };
+
+        let path = std::env::var("SAVES_DB").expect("Missing env SAVES_DB");
+        let copy = std::env::temp_dir().join(format!("saves-copy-{}.db", std::process::id()));
+        std::fs::copy(path, &copy).unwrap();
+
+        let mut store = SqliteStore::open(&copy, Encryption::from_env()).unwrap();
+        let backups = store.between(None, None).unwrap();
+        std::fs::remove_file(copy).unwrap();
+
+        assert!(!backups.is_empty());
+
+        for backup in backups {
+            let id = backup.id.unwrap_or_default();
+            let save = SaveData::decode(&backup.save_code)
+                .unwrap_or_else(|error| panic!("Backup #{}: {:?}", id, error));
+
+            assert_eq!(save.encode(), backup.save_code, "Backup #{}", id);
+        }
+    }
+
+    #[test]
+    fn corpus_formats() {
+        let formats: Vec<SaveFormat> = CORPUS
+            .iter()
+            .map(|(_, save_code)| SaveData::decode(save_code.trim_end()).unwrap().format)
+            .collect();
+
+        assert_eq!(
+            formats,
+            [
+                SaveFormat::Exported,
+                SaveFormat::Exported,
+                SaveFormat::Escaped,
+                SaveFormat::Escaped
+            ]
+        );
+    }
+
+    #[test]
+    fn edited_save_round_trip() {
+        let mut save = SaveData::decode(CORPUS[3].1.trim_end()).unwrap();
+        save.stats.cookies = 1.5e21;
+        save.buildings[0].amount += 1;
+        save.format = SaveFormat::Exported;
+
+        let decoded = SaveData::decode(&save.encode()).unwrap();
+
+        assert_eq!(decoded, save);
+    }
+
+    #[test]
+    fn normalize_pasted_save_codes() {
+        let exported = CORPUS[0].1.trim_end();
+        let escaped = CORPUS[3].1.trim_end();
+        let wrapped: String = exported
+            .as_bytes()
+            .chunks(76)
+            .map(|line| format!("  {}\r\n", std::str::from_utf8(line).unwrap()))
+            .collect();
+
+        assert_eq!(normalize_save_code(exported).unwrap(), exported);
+        assert_eq!(normalize_save_code(&wrapped).unwrap(), exported);
+        assert_eq!(
+            normalize_save_code(escaped).unwrap(),
+            escaped.replace("%3D", "=").replace("%21", "!")
+        );
+    }
+
+    #[test]
+    fn reject_malformed_save_codes() {
+        let exported = CORPUS[0].1.trim_end();
+
+        assert!(matches!(normalize_save_code(" \n"), Err(SaveError::Empty)));
+        assert!(matches!(
+            normalize_save_code(&exported[..exported.len() / 2]),
+            Err(SaveError::MissingEndMarker)
+        ));
+        assert!(normalize_save_code(&format!("{}!END!", &exported[..400])).is_err());
+        assert!(matches!(
+            normalize_save_code("bm90IGEgc2F2ZQ==%21END%21"),
+            Err(SaveError::UnsupportedVersion(_))
+        ));
+    }
+
+    #[test]
+    fn js_number_formatting() {
+        assert_eq!(js_number(0.0), "0");
+        assert_eq!(js_number(-1.0), "-1");
+        assert_eq!(js_number(123.5), "123.5");
+        assert_eq!(js_number(1690310400000.0), "1690310400000");
+        assert_eq!(js_number(1e20), "100000000000000000000");
+        assert_eq!(js_number(1e21), "1e+21");
+        assert_eq!(js_number(3.4000000000000004e28), "3.4000000000000004e+28");
+        assert_eq!(js_number(0.000001), "0.000001");
+        assert_eq!(js_number(1e-7), "1e-7");
+        assert_eq!(js_number(f64::NAN), "NaN");
+        assert_eq!(js_number(f64::NEG_INFINITY), "-Infinity");
+    }
+
+    #[test]
+    fn unescape_sequences() {
+        assert_eq!(unescape("a%3Db%3d%21").unwrap(), "a=b=!");
+
+        for invalid in ["%2", "%", "%+1", "%-1", "%G1", "% 1"] {
+            assert!(
+                matches!(unescape(invalid), Err(SaveError::InvalidEscape)),
+                "{}",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn beautify_numbers() {
+        assert_eq!(beautify(0.0), "0");
+        assert_eq!(beautify(999.9), "999");
+        assert_eq!(beautify(1234.0), "1,234");
+        assert_eq!(beautify(999_999.0), "999,999");
+        assert_eq!(beautify(1e6), "1.000 million");
+        assert_eq!(beautify(2.345e9), "2.345 billion");
+        assert_eq!(beautify(1.5e63), "1.500 vigintillion");
+        assert_eq!(beautify(1e66), "1.000e66");
+
+        // Rounding up crosses to the next name
+        assert_eq!(beautify(999_999_999.9), "1.000 billion");
+        assert_eq!(beautify(999_999_999_999_999.9), "1.000 quadrillion");
+        assert_eq!(beautify(999_999_499.0), "999.999 million");
+        assert_eq!(beautify(-999_999_999.9), "-1.000 billion");
+        assert_eq!(beautify(9.9999999e65), "1.000e66");
+
+        assert_eq!(beautify(-1234.0), "-1,234");
+        assert_eq!(beautify(-1.5e6), "-1.500 million");
+
+        assert_eq!(beautify(f64::NAN), "NaN");
+        assert_eq!(beautify(f64::INFINITY), "Infinity");
+        assert_eq!(beautify(f64::NEG_INFINITY), "-Infinity");
+    }
+}
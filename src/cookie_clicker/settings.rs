@@ -0,0 +1,11 @@
+use std::env;
+
+/// Read an `on` or `off` setting from env, `default` when it is not set
+pub fn env_switch(name: &str, default: bool) -> bool {
+    match env::var(name).as_deref() {
+        Ok("on") => true,
+        Ok("off") => false,
+        Err(_) => default,
+        Ok(_) => panic!("Invalid env {}", name),
+    }
+}
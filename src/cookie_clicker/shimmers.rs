@@ -0,0 +1,167 @@
+use std::{env, str::FromStr};
+
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+
+/// Clickable things popping up on screen, named after `Game.shimmers`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShimmerKind {
+    Golden,
+    Wrath,
+    Reindeer,
+}
+
+impl ShimmerKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Golden => "golden",
+            Self::Wrath => "wrath",
+            Self::Reindeer => "reindeer",
+        }
+    }
+}
+
+impl FromStr for ShimmerKind {
+    type Err = ();
+
+    fn from_str(kind: &str) -> Result<Self, Self::Err> {
+        match kind {
+            "golden" => Ok(Self::Golden),
+            "wrath" => Ok(Self::Wrath),
+            "reindeer" => Ok(Self::Reindeer),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Shimmer currently on screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shimmer {
+    pub id: i64,
+    pub kind: ShimmerKind,
+}
+
+impl Shimmer {
+    /// Parse an element of `Game.shimmers` reduced to its `id`, `type` and `wrath` fields
+    pub fn from_json(shimmer: &Value) -> Option<Self> {
+        let kind = match (shimmer["type"].as_str()?, shimmer["wrath"].as_i64()) {
+            ("golden", Some(wrath)) if wrath != 0 => ShimmerKind::Wrath,
+            ("golden", _) => ShimmerKind::Golden,
+            ("reindeer", _) => ShimmerKind::Reindeer,
+            _ => return None,
+        };
+
+        Some(Self {
+            id: shimmer["id"].as_i64()?,
+            kind,
+        })
+    }
+}
+
+/// Shimmer popped by the collector, along with the effect it triggered
+#[derive(Debug, Clone, PartialEq)]
+pub struct CollectedShimmer {
+    pub kind: ShimmerKind,
+    /// Name of the effect as given by `Game.shimmerTypes.golden.last`, such as `frenzy`
+    pub effect: String,
+    pub collected_at: DateTime<Utc>,
+}
+
+/// When to pop wrath cookies, which may have bad effects such as Clot or Ruin
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WrathPolicy {
+    Always,
+    Never,
+    /// Only when no buff is active, so a bad effect can't spoil an ongoing combo
+    WithoutBuffs,
+}
+
+impl WrathPolicy {
+    /// Load the policy from env `WRATH_COOKIE_POLICY`, one of `always`, `never` or `without_buffs`
+    pub fn from_env() -> Self {
+        match env::var("WRATH_COOKIE_POLICY") {
+            Ok(policy) => policy.parse().expect("Invalid env WRATH_COOKIE_POLICY"),
+            Err(_) => Self::WithoutBuffs,
+        }
+    }
+
+    /// Whether a shimmer should be popped while `buffs` buffs are active
+    pub fn should_pop(&self, kind: ShimmerKind, buffs: usize) -> bool {
+        match (kind, self) {
+            (ShimmerKind::Golden | ShimmerKind::Reindeer, _) => true,
+            (ShimmerKind::Wrath, Self::Always) => true,
+            (ShimmerKind::Wrath, Self::Never) => false,
+            (ShimmerKind::Wrath, Self::WithoutBuffs) => buffs == 0,
+        }
+    }
+}
+
+impl FromStr for WrathPolicy {
+    type Err = ();
+
+    fn from_str(policy: &str) -> Result<Self, Self::Err> {
+        match policy {
+            "always" => Ok(Self::Always),
+            "never" => Ok(Self::Never),
+            "without_buffs" => Ok(Self::WithoutBuffs),
+            _ => Err(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn parse_shimmers() {
+        let shimmers: Vec<Option<Shimmer>> = [
+            json!({ "id": 1, "type": "golden", "wrath": 0 }),
+            json!({ "id": 2, "type": "golden", "wrath": 1 }),
+            json!({ "id": 3, "type": "reindeer" }),
+            json!({ "id": 4, "type": "unknown" }),
+        ]
+        .iter()
+        .map(Shimmer::from_json)
+        .collect();
+
+        assert_eq!(
+            shimmers,
+            [
+                Some(Shimmer {
+                    id: 1,
+                    kind: ShimmerKind::Golden
+                }),
+                Some(Shimmer {
+                    id: 2,
+                    kind: ShimmerKind::Wrath
+                }),
+                Some(Shimmer {
+                    id: 3,
+                    kind: ShimmerKind::Reindeer
+                }),
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn wrath_policy() {
+        for policy in [
+            WrathPolicy::Always,
+            WrathPolicy::Never,
+            WrathPolicy::WithoutBuffs,
+        ] {
+            assert!(policy.should_pop(ShimmerKind::Golden, 2));
+            assert!(policy.should_pop(ShimmerKind::Reindeer, 2));
+        }
+
+        assert!(WrathPolicy::Always.should_pop(ShimmerKind::Wrath, 2));
+        assert!(!WrathPolicy::Never.should_pop(ShimmerKind::Wrath, 0));
+        assert!(WrathPolicy::WithoutBuffs.should_pop(ShimmerKind::Wrath, 0));
+        assert!(!WrathPolicy::WithoutBuffs.should_pop(ShimmerKind::Wrath, 1));
+        assert_eq!("never".parse(), Ok(WrathPolicy::Never));
+    }
+}
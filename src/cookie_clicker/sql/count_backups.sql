@@ -0,0 +1,4 @@
+SELECT
+    COUNT(*)
+FROM
+    backups;
\ No newline at end of file
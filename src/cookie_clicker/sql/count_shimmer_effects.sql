@@ -0,0 +1,12 @@
+SELECT
+    effect,
+    COUNT(*)
+FROM
+    shimmers
+WHERE
+    collected_at >= ?1
+GROUP BY
+    effect
+ORDER BY
+    COUNT(*) DESC,
+    effect;
\ No newline at end of file
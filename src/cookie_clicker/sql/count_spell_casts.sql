@@ -0,0 +1,12 @@
+SELECT
+    spell,
+    COUNT(*),
+    SUM(backfired)
+FROM
+    spell_casts
+WHERE
+    cast_at >= ?1
+GROUP BY
+    spell
+ORDER BY
+    spell ASC;
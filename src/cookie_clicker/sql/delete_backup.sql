@@ -0,0 +1,4 @@
+DELETE FROM
+    backups
+WHERE
+    id = ?1;
\ No newline at end of file
@@ -0,0 +1,16 @@
+SELECT
+    id,
+    save_code,
+    created_at,
+    cookies,
+    cookies_baked,
+    cookies_per_second,
+    prestige,
+    buildings,
+    heavenly_chips,
+    label,
+    content_hash
+FROM
+    backups
+WHERE
+    id = ?1;
\ No newline at end of file
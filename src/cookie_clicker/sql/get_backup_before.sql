@@ -0,0 +1,21 @@
+SELECT
+    id,
+    save_code,
+    created_at,
+    cookies,
+    cookies_baked,
+    cookies_per_second,
+    prestige,
+    buildings,
+    heavenly_chips,
+    label,
+    content_hash
+FROM
+    backups
+WHERE
+    created_at <= ?1
+ORDER BY
+    created_at DESC,
+    id DESC
+LIMIT
+    1;
\ No newline at end of file
@@ -0,0 +1,7 @@
+SELECT
+    id,
+    save_code,
+    content_hash,
+    created_at
+FROM
+    backups;
\ No newline at end of file
@@ -0,0 +1,26 @@
+SELECT
+    id,
+    save_code,
+    created_at,
+    cookies,
+    cookies_baked,
+    cookies_per_second,
+    prestige,
+    buildings,
+    heavenly_chips,
+    label,
+    content_hash
+FROM
+    backups
+WHERE
+    (
+        ?1 IS NULL
+        OR created_at >= ?1
+    )
+    AND (
+        ?2 IS NULL
+        OR created_at <= ?2
+    )
+ORDER BY
+    created_at,
+    id;
\ No newline at end of file
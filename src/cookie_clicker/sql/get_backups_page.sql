@@ -0,0 +1,19 @@
+SELECT
+    id,
+    save_code,
+    created_at,
+    cookies,
+    cookies_baked,
+    cookies_per_second,
+    prestige,
+    buildings,
+    heavenly_chips,
+    label,
+    content_hash
+FROM
+    backups
+ORDER BY
+    created_at DESC,
+    id DESC
+LIMIT
+    ?1 OFFSET ?2;
\ No newline at end of file
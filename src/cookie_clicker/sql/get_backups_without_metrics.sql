@@ -0,0 +1,10 @@
+SELECT
+    id,
+    save_code,
+    content_hash,
+    created_at
+FROM
+    backups
+WHERE
+    cookies IS NULL
+    AND NOT metrics_unavailable;
\ No newline at end of file
@@ -0,0 +1,9 @@
+SELECT
+    content_hash
+FROM
+    backups
+ORDER BY
+    created_at DESC,
+    id DESC
+LIMIT
+    1;
\ No newline at end of file
@@ -0,0 +1,9 @@
+SELECT
+    symbol,
+    side,
+    quantity,
+    price
+FROM
+    market_trades
+ORDER BY
+    id ASC;
@@ -0,0 +1,19 @@
+SELECT
+    id,
+    save_code,
+    created_at,
+    cookies,
+    cookies_baked,
+    cookies_per_second,
+    prestige,
+    buildings,
+    heavenly_chips,
+    label,
+    content_hash
+FROM
+    backups
+WHERE
+    label IS NOT NULL
+ORDER BY
+    created_at DESC,
+    id DESC;
\ No newline at end of file
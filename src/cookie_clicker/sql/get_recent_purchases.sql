@@ -0,0 +1,13 @@
+SELECT
+    kind,
+    name,
+    price,
+    cps_gain,
+    bought_at
+FROM
+    purchases
+ORDER BY
+    bought_at DESC,
+    id DESC
+LIMIT
+    ?1;
\ No newline at end of file
@@ -0,0 +1,11 @@
+SELECT
+    kind,
+    effect,
+    collected_at
+FROM
+    shimmers
+ORDER BY
+    collected_at DESC,
+    id DESC
+LIMIT
+    ?1;
\ No newline at end of file
@@ -0,0 +1,7 @@
+SELECT
+    id,
+    created_at
+FROM
+    backups
+WHERE
+    label IS NULL;
\ No newline at end of file
@@ -1,4 +1,14 @@
 INSERT INTO
-    backups (save_code, created_at)
+    backups (
+        save_code,
+        content_hash,
+        created_at,
+        cookies,
+        cookies_baked,
+        cookies_per_second,
+        prestige,
+        buildings,
+        heavenly_chips
+    )
 VALUES
-    (?1, ?2);
\ No newline at end of file
+    (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9);
\ No newline at end of file
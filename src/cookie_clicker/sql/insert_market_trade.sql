@@ -0,0 +1,4 @@
+INSERT INTO
+    market_trades (symbol, side, quantity, price, profit, traded_at)
+VALUES
+    (?1, ?2, ?3, ?4, ?5, ?6);
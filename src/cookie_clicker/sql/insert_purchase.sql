@@ -0,0 +1,4 @@
+INSERT INTO
+    purchases (kind, name, price, cps_gain, bought_at)
+VALUES
+    (?1, ?2, ?3, ?4, ?5);
\ No newline at end of file
@@ -0,0 +1,4 @@
+INSERT INTO
+    shimmers (kind, effect, collected_at)
+VALUES
+    (?1, ?2, ?3);
\ No newline at end of file
@@ -0,0 +1,4 @@
+INSERT INTO
+    spell_casts (spell, backfired, effect, cookies, cast_at)
+VALUES
+    (?1, ?2, ?3, ?4, ?5);
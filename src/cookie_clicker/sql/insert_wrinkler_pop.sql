@@ -0,0 +1,4 @@
+INSERT INTO
+    wrinkler_pops (wrinklers, reclaimed, popped_at)
+VALUES
+    (?1, ?2, ?3);
\ No newline at end of file
@@ -0,0 +1,6 @@
+ALTER TABLE "backups" ADD COLUMN "cookies" REAL;
+ALTER TABLE "backups" ADD COLUMN "cookies_baked" REAL;
+ALTER TABLE "backups" ADD COLUMN "cookies_per_second" REAL;
+ALTER TABLE "backups" ADD COLUMN "prestige" REAL;
+ALTER TABLE "backups" ADD COLUMN "buildings" INTEGER;
+ALTER TABLE "backups" ADD COLUMN "heavenly_chips" REAL;
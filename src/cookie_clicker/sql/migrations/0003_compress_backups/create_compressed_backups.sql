@@ -0,0 +1,29 @@
+CREATE TABLE "compressed_backups" (
+	"id" INTEGER NOT NULL UNIQUE,
+	"save_code" BLOB NOT NULL,
+	"content_hash" TEXT NOT NULL,
+	"created_at" TEXT NOT NULL,
+	"cookies" REAL,
+	"cookies_baked" REAL,
+	"cookies_per_second" REAL,
+	"prestige" REAL,
+	"buildings" INTEGER,
+	"heavenly_chips" REAL,
+	PRIMARY KEY("id" AUTOINCREMENT)
+);
+
+INSERT INTO
+    "compressed_backups"
+SELECT
+    "id",
+    "save_code",
+    '',
+    "created_at",
+    "cookies",
+    "cookies_baked",
+    "cookies_per_second",
+    "prestige",
+    "buildings",
+    "heavenly_chips"
+FROM
+    "backups";
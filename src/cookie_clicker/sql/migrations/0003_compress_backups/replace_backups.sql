@@ -0,0 +1,5 @@
+DROP TABLE "backups";
+
+ALTER TABLE "compressed_backups" RENAME TO "backups";
+
+CREATE INDEX "backups_created_at" ON "backups" ("created_at" DESC);
@@ -0,0 +1,3 @@
+ALTER TABLE "backups" ADD COLUMN "label" TEXT;
+
+CREATE UNIQUE INDEX "backups_label" ON "backups" ("label");
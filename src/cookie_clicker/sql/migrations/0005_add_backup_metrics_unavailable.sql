@@ -0,0 +1 @@
+ALTER TABLE "backups" ADD COLUMN "metrics_unavailable" INTEGER NOT NULL DEFAULT 0;
\ No newline at end of file
@@ -0,0 +1,9 @@
+CREATE TABLE "shimmers" (
+	"id" INTEGER NOT NULL UNIQUE,
+	"kind" TEXT NOT NULL,
+	"effect" TEXT NOT NULL,
+	"collected_at" TEXT NOT NULL,
+	PRIMARY KEY("id" AUTOINCREMENT)
+);
+
+CREATE INDEX "shimmers_collected_at" ON "shimmers" ("collected_at" DESC);
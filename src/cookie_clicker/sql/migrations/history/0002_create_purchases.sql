@@ -0,0 +1,11 @@
+CREATE TABLE "purchases" (
+	"id" INTEGER NOT NULL UNIQUE,
+	"kind" TEXT NOT NULL,
+	"name" TEXT NOT NULL,
+	"price" REAL NOT NULL,
+	"cps_gain" REAL NOT NULL,
+	"bought_at" TEXT NOT NULL,
+	PRIMARY KEY("id" AUTOINCREMENT)
+);
+
+CREATE INDEX "purchases_bought_at" ON "purchases" ("bought_at" DESC);
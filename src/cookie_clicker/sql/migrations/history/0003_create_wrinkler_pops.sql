@@ -0,0 +1,9 @@
+CREATE TABLE "wrinkler_pops" (
+	"id" INTEGER NOT NULL UNIQUE,
+	"wrinklers" INTEGER NOT NULL,
+	"reclaimed" REAL NOT NULL,
+	"popped_at" TEXT NOT NULL,
+	PRIMARY KEY("id" AUTOINCREMENT)
+);
+
+CREATE INDEX "wrinkler_pops_popped_at" ON "wrinkler_pops" ("popped_at" DESC);
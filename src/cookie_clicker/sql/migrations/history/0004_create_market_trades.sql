@@ -0,0 +1,12 @@
+CREATE TABLE "market_trades" (
+	"id" INTEGER NOT NULL UNIQUE,
+	"symbol" TEXT NOT NULL,
+	"side" TEXT NOT NULL,
+	"quantity" INTEGER NOT NULL,
+	"price" REAL NOT NULL,
+	"profit" REAL NOT NULL,
+	"traded_at" TEXT NOT NULL,
+	PRIMARY KEY("id" AUTOINCREMENT)
+);
+
+CREATE INDEX "market_trades_traded_at" ON "market_trades" ("traded_at" DESC);
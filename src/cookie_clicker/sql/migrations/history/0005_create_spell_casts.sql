@@ -0,0 +1,11 @@
+CREATE TABLE "spell_casts" (
+	"id" INTEGER NOT NULL UNIQUE,
+	"spell" TEXT NOT NULL,
+	"backfired" INTEGER NOT NULL,
+	"effect" TEXT,
+	"cookies" REAL NOT NULL,
+	"cast_at" TEXT NOT NULL,
+	PRIMARY KEY("id" AUTOINCREMENT)
+);
+
+CREATE INDEX "spell_casts_cast_at" ON "spell_casts" ("cast_at" DESC);
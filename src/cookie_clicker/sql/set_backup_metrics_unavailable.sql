@@ -0,0 +1,6 @@
+UPDATE
+    backups
+SET
+    metrics_unavailable = 1
+WHERE
+    id = ?1;
\ No newline at end of file
@@ -0,0 +1,6 @@
+SELECT
+    COALESCE(SUM(profit), 0)
+FROM
+    market_trades
+WHERE
+    traded_at >= ?1;
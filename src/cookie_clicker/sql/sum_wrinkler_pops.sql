@@ -0,0 +1,7 @@
+SELECT
+    COALESCE(SUM(wrinklers), 0),
+    COALESCE(SUM(reclaimed), 0)
+FROM
+    wrinkler_pops
+WHERE
+    popped_at >= ?1;
\ No newline at end of file
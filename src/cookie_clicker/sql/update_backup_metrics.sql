@@ -0,0 +1,11 @@
+UPDATE
+    backups
+SET
+    cookies = ?2,
+    cookies_baked = ?3,
+    cookies_per_second = ?4,
+    prestige = ?5,
+    buildings = ?6,
+    heavenly_chips = ?7
+WHERE
+    id = ?1;
\ No newline at end of file
@@ -0,0 +1,6 @@
+UPDATE
+    backups
+SET
+    save_code = ?2
+WHERE
+    id = ?1;
\ No newline at end of file
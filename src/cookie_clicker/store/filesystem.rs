@@ -0,0 +1,243 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+
+use super::{
+    super::{
+        backup::{Backup, BackupError, BackupMetrics, BackupResult},
+        compression::content_hash,
+        save::SaveData,
+    },
+    BackupStore,
+};
+
+/// Nanoseconds keep file dates equal to backup dates
+const TIMESTAMP_FORMAT: &str = "%Y%m%dT%H%M%S%.9fZ";
+
+/// Metadata of a stored backup, parsed from its file name
+#[derive(Debug)]
+struct BackupFile {
+    id: i64,
+    saved_at: DateTime<Utc>,
+    label: Option<String>,
+    path: PathBuf,
+}
+
+impl BackupFile {
+    /// Parse a file name such as `20230401T120000.000000000Z_12.txt`, pinned backups add `_<label>`
+    fn parse(path: PathBuf) -> Option<Self> {
+        let name = path.file_name()?.to_str()?.strip_suffix(".txt")?;
+        let mut parts = name.splitn(3, '_');
+
+        let saved_at = NaiveDateTime::parse_from_str(parts.next()?, TIMESTAMP_FORMAT).ok()?;
+        let id = parts.next()?.parse().ok()?;
+        let label = parts.next().map(str::to_string);
+
+        Some(Self {
+            id,
+            saved_at: Utc.from_utc_datetime(&saved_at),
+            label,
+            path,
+        })
+    }
+
+    fn file_name(id: i64, saved_at: DateTime<Utc>, label: Option<&str>) -> String {
+        let timestamp = saved_at.format(TIMESTAMP_FORMAT);
+
+        match label {
+            Some(label) => format!("{}_{}_{}.txt", timestamp, id, label),
+            None => format!("{}_{}.txt", timestamp, id),
+        }
+    }
+}
+
+/// Backups stored as one plain text file per save in a directory
+///
+/// Files contain the save code only, so they can be imported in the game as is.
+/// Metrics are decoded again when reading, which loses the cookies per second.
+#[derive(Debug)]
+pub struct FilesystemStore {
+    directory: PathBuf,
+}
+
+impl FilesystemStore {
+    pub fn open(directory: impl AsRef<Path>) -> BackupResult<Self> {
+        let directory = directory.as_ref().to_path_buf();
+        fs::create_dir_all(&directory).map_err(BackupError::IoError)?;
+
+        Ok(Self { directory })
+    }
+
+    /// List stored backups oldest first, ignoring unrelated files
+    fn files(&self) -> BackupResult<Vec<BackupFile>> {
+        let mut files = fs::read_dir(&self.directory)
+            .map_err(BackupError::IoError)?
+            .map(|entry| entry.map(|entry| BackupFile::parse(entry.path())))
+            .filter_map(Result::transpose)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(BackupError::IoError)?;
+
+        files.sort_by_key(|file| (file.saved_at, file.id));
+
+        Ok(files)
+    }
+
+    fn read_save_code(file: &BackupFile) -> BackupResult<String> {
+        fs::read_to_string(&file.path).map_err(BackupError::IoError)
+    }
+
+    fn load(file: BackupFile) -> BackupResult<Backup> {
+        let save_code = Self::read_save_code(&file)?;
+        let metrics = SaveData::decode(&save_code)
+            .ok()
+            .map(|save| BackupMetrics::from_save(&save));
+
+        Ok(Backup {
+            id: Some(file.id),
+            saved_at: file.saved_at,
+            save_code,
+            metrics,
+            label: file.label,
+        })
+    }
+}
+
+impl BackupStore for FilesystemStore {
+    fn insert(&mut self, backup: &Backup, _content_hash: &str) -> BackupResult<i64> {
+        let id = self
+            .files()?
+            .iter()
+            .map(|file| file.id)
+            .max()
+            .map_or(1, |id| id + 1);
+        let path = self
+            .directory
+            .join(BackupFile::file_name(id, backup.saved_at, None));
+
+        fs::write(path, &backup.save_code).map_err(BackupError::IoError)?;
+
+        Ok(id)
+    }
+
+    fn latest_content_hash(&mut self) -> BackupResult<Option<String>> {
+        self.files()?
+            .last()
+            .map(|file| Ok(content_hash(&Self::read_save_code(file)?)))
+            .transpose()
+    }
+
+    fn latest(&mut self) -> BackupResult<Option<Backup>> {
+        self.files()?.pop().map(Self::load).transpose()
+    }
+
+    fn get(&mut self, id: i64) -> BackupResult<Option<Backup>> {
+        self.files()?
+            .into_iter()
+            .find(|file| file.id == id)
+            .map(Self::load)
+            .transpose()
+    }
+
+    fn latest_before(&mut self, instant: DateTime<Utc>) -> BackupResult<Option<Backup>> {
+        self.files()?
+            .into_iter()
+            .filter(|file| file.saved_at <= instant)
+            .max_by_key(|file| file.saved_at)
+            .map(Self::load)
+            .transpose()
+    }
+
+    fn list(&mut self, offset: usize, limit: usize) -> BackupResult<Vec<Backup>> {
+        self.files()?
+            .into_iter()
+            .rev()
+            .skip(offset)
+            .take(limit)
+            .map(Self::load)
+            .collect()
+    }
+
+    fn between(
+        &mut self,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> BackupResult<Vec<Backup>> {
+        self.files()?
+            .into_iter()
+            .filter(|file| {
+                from.is_none_or(|from| file.saved_at >= from)
+                    && to.is_none_or(|to| file.saved_at <= to)
+            })
+            .map(Self::load)
+            .collect()
+    }
+
+    fn count(&mut self) -> BackupResult<usize> {
+        Ok(self.files()?.len())
+    }
+
+    fn get_by_label(&mut self, label: &str) -> BackupResult<Option<Backup>> {
+        self.files()?
+            .into_iter()
+            .find(|file| file.label.as_deref() == Some(label))
+            .map(Self::load)
+            .transpose()
+    }
+
+    fn pinned(&mut self) -> BackupResult<Vec<Backup>> {
+        self.files()?
+            .into_iter()
+            .rev()
+            .filter(|file| file.label.is_some())
+            .map(Self::load)
+            .collect()
+    }
+
+    fn set_label(&mut self, id: i64, label: Option<&str>) -> BackupResult<bool> {
+        if label.is_some_and(|label| label.contains(['/', '\\', '\0'])) {
+            return Err(BackupError::IoError(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Labels cannot contain path separators",
+            )));
+        }
+
+        let file = match self.files()?.into_iter().find(|file| file.id == id) {
+            Some(file) => file,
+            None => return Ok(false),
+        };
+
+        let path = self
+            .directory
+            .join(BackupFile::file_name(id, file.saved_at, label));
+
+        fs::rename(&file.path, path).map_err(BackupError::IoError)?;
+
+        Ok(true)
+    }
+
+    fn unpinned_dates(&mut self) -> BackupResult<Vec<(i64, DateTime<Utc>)>> {
+        Ok(self
+            .files()?
+            .into_iter()
+            .filter(|file| file.label.is_none())
+            .map(|file| (file.id, file.saved_at))
+            .collect())
+    }
+
+    fn delete(&mut self, ids: &[i64]) -> BackupResult<()> {
+        for file in self.files()? {
+            if ids.contains(&file.id) {
+                fs::remove_file(&file.path).map_err(BackupError::IoError)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn rotate_key(&mut self) -> BackupResult<usize> {
+        Err(BackupError::EncryptionDisabled)
+    }
+}
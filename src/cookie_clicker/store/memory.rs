@@ -0,0 +1,153 @@
+use chrono::{DateTime, Utc};
+
+use super::{
+    super::backup::{Backup, BackupError, BackupResult},
+    BackupStore,
+};
+
+/// Backups kept in memory only, lost when the bot stops. Mostly useful for tests
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    /// Backups with their content hash, oldest first
+    backups: Vec<(Backup, String)>,
+    last_id: i64,
+}
+
+impl MemoryStore {
+    fn find(&self, predicate: impl Fn(&Backup) -> bool) -> Option<Backup> {
+        self.backups
+            .iter()
+            .rev()
+            .map(|(backup, _)| backup)
+            .find(|backup| predicate(backup))
+            .cloned()
+    }
+}
+
+impl BackupStore for MemoryStore {
+    fn insert(&mut self, backup: &Backup, content_hash: &str) -> BackupResult<i64> {
+        self.last_id += 1;
+
+        // Like other stores, labels are only set through `set_label`
+        let mut backup = backup.clone();
+        backup.id = Some(self.last_id);
+        backup.label = None;
+
+        let position = self
+            .backups
+            .partition_point(|(stored, _)| stored.saved_at <= backup.saved_at);
+        self.backups
+            .insert(position, (backup, content_hash.to_string()));
+
+        Ok(self.last_id)
+    }
+
+    fn latest_content_hash(&mut self) -> BackupResult<Option<String>> {
+        Ok(self
+            .backups
+            .last()
+            .map(|(_, content_hash)| content_hash.clone()))
+    }
+
+    fn latest(&mut self) -> BackupResult<Option<Backup>> {
+        Ok(self.find(|_| true))
+    }
+
+    fn get(&mut self, id: i64) -> BackupResult<Option<Backup>> {
+        Ok(self.find(|backup| backup.id == Some(id)))
+    }
+
+    fn latest_before(&mut self, instant: DateTime<Utc>) -> BackupResult<Option<Backup>> {
+        Ok(self
+            .backups
+            .iter()
+            .map(|(backup, _)| backup)
+            .filter(|backup| backup.saved_at <= instant)
+            .max_by_key(|backup| backup.saved_at)
+            .cloned())
+    }
+
+    fn list(&mut self, offset: usize, limit: usize) -> BackupResult<Vec<Backup>> {
+        Ok(self
+            .backups
+            .iter()
+            .rev()
+            .skip(offset)
+            .take(limit)
+            .map(|(backup, _)| backup.clone())
+            .collect())
+    }
+
+    fn between(
+        &mut self,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> BackupResult<Vec<Backup>> {
+        Ok(self
+            .backups
+            .iter()
+            .map(|(backup, _)| backup)
+            .filter(|backup| {
+                from.is_none_or(|from| backup.saved_at >= from)
+                    && to.is_none_or(|to| backup.saved_at <= to)
+            })
+            .cloned()
+            .collect())
+    }
+
+    fn count(&mut self) -> BackupResult<usize> {
+        Ok(self.backups.len())
+    }
+
+    fn get_by_label(&mut self, label: &str) -> BackupResult<Option<Backup>> {
+        Ok(self.find(|backup| backup.label.as_deref() == Some(label)))
+    }
+
+    fn pinned(&mut self) -> BackupResult<Vec<Backup>> {
+        Ok(self
+            .backups
+            .iter()
+            .rev()
+            .map(|(backup, _)| backup)
+            .filter(|backup| backup.label.is_some())
+            .cloned()
+            .collect())
+    }
+
+    fn set_label(&mut self, id: i64, label: Option<&str>) -> BackupResult<bool> {
+        let backup = self
+            .backups
+            .iter_mut()
+            .map(|(backup, _)| backup)
+            .find(|backup| backup.id == Some(id));
+
+        match backup {
+            Some(backup) => {
+                backup.label = label.map(str::to_string);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    fn unpinned_dates(&mut self) -> BackupResult<Vec<(i64, DateTime<Utc>)>> {
+        Ok(self
+            .backups
+            .iter()
+            .map(|(backup, _)| backup)
+            .filter(|backup| backup.label.is_none())
+            .filter_map(|backup| Some((backup.id?, backup.saved_at)))
+            .collect())
+    }
+
+    fn delete(&mut self, ids: &[i64]) -> BackupResult<()> {
+        self.backups
+            .retain(|(backup, _)| !backup.id.is_some_and(|id| ids.contains(&id)));
+
+        Ok(())
+    }
+
+    fn rotate_key(&mut self) -> BackupResult<usize> {
+        Err(BackupError::EncryptionDisabled)
+    }
+}
@@ -0,0 +1,296 @@
+use std::{env, fmt, path::PathBuf};
+
+use chrono::{DateTime, Utc};
+use log::warn;
+
+use super::{
+    backup::{Backup, BackupResult},
+    encryption::Encryption,
+};
+
+mod filesystem;
+pub use filesystem::FilesystemStore;
+
+mod memory;
+pub use memory::MemoryStore;
+
+mod sqlite;
+pub use sqlite::SqliteStore;
+
+/// Storage backend for backups
+///
+/// Stores only persist backups, deduplication, pinning rules and pruning are
+/// implemented once in `Backups` on top of them.
+pub trait BackupStore: fmt::Debug + Send {
+    /// Store a backup along with the hash of its save code, returns its id
+    fn insert(&mut self, backup: &Backup, content_hash: &str) -> BackupResult<i64>;
+
+    /// Content hash of the most recent backup
+    fn latest_content_hash(&mut self) -> BackupResult<Option<String>>;
+
+    fn latest(&mut self) -> BackupResult<Option<Backup>>;
+
+    fn get(&mut self, id: i64) -> BackupResult<Option<Backup>>;
+
+    /// Get the most recent backup taken at or before `instant`
+    fn latest_before(&mut self, instant: DateTime<Utc>) -> BackupResult<Option<Backup>>;
+
+    /// List backups newest first
+    fn list(&mut self, offset: usize, limit: usize) -> BackupResult<Vec<Backup>>;
+
+    /// List the backups taken between `from` and `to` included, oldest first
+    fn between(
+        &mut self,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> BackupResult<Vec<Backup>>;
+
+    fn count(&mut self) -> BackupResult<usize>;
+
+    fn get_by_label(&mut self, label: &str) -> BackupResult<Option<Backup>>;
+
+    /// List pinned backups newest first
+    fn pinned(&mut self) -> BackupResult<Vec<Backup>>;
+
+    /// Set or clear the label of a backup, returns whether the backup exists
+    fn set_label(&mut self, id: i64, label: Option<&str>) -> BackupResult<bool>;
+
+    /// Ids and dates of the backups without a label, the only ones that can be pruned
+    fn unpinned_dates(&mut self) -> BackupResult<Vec<(i64, DateTime<Utc>)>>;
+
+    fn delete(&mut self, ids: &[i64]) -> BackupResult<()>;
+
+    /// Encrypt every backup with the current key, including the ones stored before encryption
+    /// was enabled, returns how many backups were encrypted again
+    fn rotate_key(&mut self) -> BackupResult<usize>;
+}
+
+/// Open the store selected by env `BACKUP_STORE`: `sqlite` (default), `filesystem` or `memory`
+pub fn from_env() -> BackupResult<Box<dyn BackupStore>> {
+    let store = env::var("BACKUP_STORE").unwrap_or_else(|_| "sqlite".to_string());
+
+    let data_path = || {
+        let data_path = env::var("PERSISTENT_DATA_PATH").expect("Missing env PERSISTENT_DATA_PATH");
+
+        PathBuf::from(data_path)
+    };
+
+    let store: Box<dyn BackupStore> = match store.as_str() {
+        "sqlite" => Box::new(SqliteStore::open(
+            data_path().join("saves.db"),
+            Encryption::from_env(),
+        )?),
+        // Saves are kept as plain text files, so that they can be imported in the game as is
+        "filesystem" if Encryption::from_env().is_some() => {
+            panic!("Invalid env BACKUP_STORE, the filesystem store cannot encrypt backups")
+        }
+        "filesystem" => Box::new(FilesystemStore::open(data_path().join("saves"))?),
+        "memory" => {
+            warn!("BACKUP_STORE=memory keeps backups in memory only, they are lost when the bot stops");
+
+            Box::new(MemoryStore::default())
+        }
+        _ => panic!("Invalid env BACKUP_STORE"),
+    };
+
+    Ok(store)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use chrono::Duration;
+    use rusqlite::Connection;
+
+    use super::super::{
+        backup::{BackupError, Backups},
+        retention::RetentionPolicy,
+    };
+    use super::*;
+
+    fn backup(save_code: &str, age: Duration) -> Backup {
+        let mut backup = Backup::new(save_code.to_string());
+        backup.saved_at = Utc::now() - age;
+
+        backup
+    }
+
+    /// Run the same scenario against a store, so that every backend behaves the same
+    fn check_store(store: Box<dyn BackupStore>) {
+        let mut backups = Backups::with_store(store, RetentionPolicy::parse("1d:7d").unwrap());
+
+        assert!(backups.add(backup("first", Duration::days(30))).unwrap());
+        assert!(backups.add(backup("second", Duration::days(20))).unwrap());
+        assert!(!backups.add(backup("second", Duration::days(20))).unwrap());
+        assert!(backups.add(backup("third", Duration::days(2))).unwrap());
+        assert!(backups.add(backup("fourth", Duration::zero())).unwrap());
+        assert_eq!(backups.count().unwrap(), 4);
+
+        let latest = backups.latest_backup().unwrap().unwrap();
+        assert_eq!(latest.save_code, "fourth");
+
+        let page: Vec<String> = backups
+            .list(1, 2)
+            .unwrap()
+            .into_iter()
+            .map(|backup| backup.save_code)
+            .collect();
+        assert_eq!(page, ["second", "first"]);
+
+        let before = backups
+            .latest_before(Utc::now() - Duration::days(10))
+            .unwrap()
+            .unwrap();
+        assert_eq!(before.save_code, "second");
+
+        let range: Vec<String> = backups
+            .between(
+                Some(Utc::now() - Duration::days(25)),
+                Some(Utc::now() - Duration::days(1)),
+            )
+            .unwrap()
+            .into_iter()
+            .map(|backup| backup.save_code)
+            .collect();
+        assert_eq!(range, ["second", "third"]);
+        assert_eq!(backups.between(None, None).unwrap().len(), 4);
+
+        let first = backups.list(3, 1).unwrap().remove(0);
+        assert!(backups.pin(first.id.unwrap(), "start").unwrap());
+        assert!(!backups.pin(1000, "missing").unwrap());
+        assert!(matches!(
+            backups.pin(latest.id.unwrap(), "start"),
+            Err(BackupError::LabelAlreadyUsed(_))
+        ));
+
+        let pinned = backups.get_by_label("start").unwrap().unwrap();
+        assert_eq!(pinned.id, first.id);
+        assert_eq!(pinned.save_code, "first");
+        assert_eq!(backups.pinned().unwrap().len(), 1);
+
+        // Only the unpinned expired backup is pruned
+        assert_eq!(backups.prune().unwrap(), 1);
+        assert!(backups.get(first.id.unwrap()).unwrap().is_some());
+
+        assert!(backups.unpin("start").unwrap());
+        assert!(!backups.unpin("start").unwrap());
+        assert_eq!(backups.prune().unwrap(), 1);
+        assert_eq!(backups.count().unwrap(), 2);
+    }
+
+    #[test]
+    fn memory_store() {
+        check_store(Box::new(MemoryStore::default()));
+    }
+
+    #[test]
+    fn sqlite_store() {
+        let connection = Connection::open_in_memory().unwrap();
+
+        check_store(Box::new(
+            SqliteStore::with_connection(connection, None).unwrap(),
+        ));
+    }
+
+    #[test]
+    fn encrypted_sqlite_store() {
+        let connection = Connection::open_in_memory().unwrap();
+        let encryption = Encryption::new(&[1; 32], &[]);
+
+        check_store(Box::new(
+            SqliteStore::with_connection(connection, encryption).unwrap(),
+        ));
+    }
+
+    #[test]
+    fn rotate_sqlite_key() {
+        let path = env::temp_dir().join(format!("saves-{}.db", std::process::id()));
+        let old_key = [1; 32];
+        let key = [2; 32];
+
+        let mut store = SqliteStore::open(&path, None).unwrap();
+        store
+            .insert(&backup("plain", Duration::days(2)), "plain")
+            .unwrap();
+        assert!(matches!(
+            store.rotate_key(),
+            Err(BackupError::EncryptionDisabled)
+        ));
+
+        // Plain backups are encrypted as soon as a key is configured
+        let mut store = SqliteStore::open(&path, Encryption::new(&old_key, &[])).unwrap();
+        assert_eq!(store.latest().unwrap().unwrap().save_code, "plain");
+        store
+            .insert(&backup("old", Duration::days(1)), "old")
+            .unwrap();
+        assert!(matches!(
+            SqliteStore::open(&path, None).unwrap().get(1),
+            Err(BackupError::DecryptionFailed(_))
+        ));
+
+        let mut store = SqliteStore::open(&path, Encryption::new(&key, &[&old_key])).unwrap();
+        store
+            .insert(&backup("new", Duration::zero()), "new")
+            .unwrap();
+        assert_eq!(store.list(0, 10).unwrap().len(), 3);
+        assert_eq!(store.rotate_key().unwrap(), 2);
+        assert_eq!(store.rotate_key().unwrap(), 0);
+
+        // Old keys are not needed anymore once rotated
+        let mut store = SqliteStore::open(&path, Encryption::new(&key, &[])).unwrap();
+        let save_codes: Vec<String> = store
+            .list(0, 10)
+            .unwrap()
+            .into_iter()
+            .map(|backup| backup.save_code)
+            .collect();
+        assert_eq!(save_codes, ["new", "old", "plain"]);
+
+        let mut store = SqliteStore::open(&path, None).unwrap();
+        assert!(matches!(
+            store.latest(),
+            Err(BackupError::DecryptionFailed(_))
+        ));
+
+        // Save codes are bound to their row, even when moved along with their content hash
+        let connection = Connection::open(&path).unwrap();
+        let rows: Vec<(Vec<u8>, String)> = connection
+            .prepare("SELECT save_code, content_hash FROM backups WHERE id IN (2, 3) ORDER BY id")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        for (id, (save_code, content_hash)) in [3, 2].into_iter().zip(rows) {
+            connection
+                .execute(
+                    "UPDATE backups SET save_code = ?2, content_hash = ?3 WHERE id = ?1",
+                    rusqlite::params![id, save_code, content_hash],
+                )
+                .unwrap();
+        }
+
+        let mut store = SqliteStore::open(&path, Encryption::new(&key, &[])).unwrap();
+        assert_eq!(store.get(1).unwrap().unwrap().save_code, "plain");
+        assert!(matches!(
+            store.get(2),
+            Err(BackupError::DecryptionFailed(_))
+        ));
+        assert!(matches!(
+            store.get(3),
+            Err(BackupError::DecryptionFailed(_))
+        ));
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn filesystem_store() {
+        let directory = env::temp_dir().join(format!("backups-{}", std::process::id()));
+
+        check_store(Box::new(FilesystemStore::open(&directory).unwrap()));
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
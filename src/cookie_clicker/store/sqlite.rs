@@ -0,0 +1,456 @@
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use log::info;
+use rusqlite::{params, types::Type, Connection, OptionalExtension, Row};
+
+use super::{
+    super::{
+        backup::{Backup, BackupError, BackupMetrics, BackupResult},
+        compression::{compress_save_code, decompress_save_code},
+        encryption::{self, DecryptionError, Encryption},
+        migrations,
+        save::SaveData,
+    },
+    BackupStore,
+};
+
+/// Associated data binding an encrypted save code to its row
+///
+/// The id is unique and never reused, so a save code cannot be moved to another
+/// row even along with its content hash.
+fn row_aad(id: i64, saved_at: &DateTime<Utc>, content_hash: &str) -> Vec<u8> {
+    format!("{}|{}|{}", id, saved_at.to_rfc3339(), content_hash).into_bytes()
+}
+
+/// Read a save code compressed with `compress_save_code`, and possibly encrypted, from a row
+fn decompress_row_save_code(
+    row: &Row,
+    column: usize,
+    aad: &[u8],
+    encryption: Option<&Encryption>,
+) -> rusqlite::Result<String> {
+    let stored: Vec<u8> = row.get(column)?;
+
+    let compressed = encryption::decrypt(encryption, &stored, aad).map_err(|error| {
+        rusqlite::Error::FromSqlConversionFailure(column, Type::Blob, Box::new(error))
+    })?;
+
+    decompress_save_code(&compressed).map_err(|error| {
+        rusqlite::Error::FromSqlConversionFailure(column, Type::Blob, Box::new(error))
+    })
+}
+
+/// Surface decryption failures of row conversions as `BackupError::DecryptionFailed`
+fn map_row_error(error: rusqlite::Error) -> BackupError {
+    match error {
+        rusqlite::Error::FromSqlConversionFailure(column, kind, error) => {
+            match error.downcast::<DecryptionError>() {
+                Ok(error) => BackupError::DecryptionFailed(*error),
+                Err(error) => BackupError::RusqliteError(
+                    rusqlite::Error::FromSqlConversionFailure(column, kind, error),
+                ),
+            }
+        }
+        error => BackupError::RusqliteError(error),
+    }
+}
+
+/// Build metrics from the columns starting at `first_column`, `None` when they are not populated
+fn metrics_from_row(row: &Row, first_column: usize) -> rusqlite::Result<Option<BackupMetrics>> {
+    let cookies: Option<f64> = row.get(first_column)?;
+
+    cookies
+        .map(|cookies| {
+            Ok(BackupMetrics {
+                cookies,
+                cookies_baked: row.get(first_column + 1)?,
+                cookies_per_second: row.get(first_column + 2)?,
+                prestige: row.get(first_column + 3)?,
+                buildings: row.get(first_column + 4)?,
+                heavenly_chips: row.get(first_column + 5)?,
+            })
+        })
+        .transpose()
+}
+
+/// Build a `Backup` from a row selecting `id`, `save_code`, `created_at`, the metrics columns, `label` and `content_hash`
+fn backup_from_row(row: &Row, encryption: Option<&Encryption>) -> rusqlite::Result<Backup> {
+    let id = row.get(0)?;
+    let saved_at = row.get(2)?;
+    let content_hash: String = row.get(10)?;
+
+    Ok(Backup {
+        id: Some(id),
+        save_code: decompress_row_save_code(
+            row,
+            1,
+            &row_aad(id, &saved_at, &content_hash),
+            encryption,
+        )?,
+        saved_at,
+        metrics: metrics_from_row(row, 3)?,
+        label: row.get(9)?,
+    })
+}
+
+/// Backups stored in a SQLite database, with save codes optionally encrypted
+#[derive(Debug)]
+pub struct SqliteStore {
+    connection: Connection,
+    encryption: Option<Encryption>,
+}
+
+impl SqliteStore {
+    pub fn open(path: impl AsRef<Path>, encryption: Option<Encryption>) -> BackupResult<Self> {
+        let connection = Connection::open(path).map_err(BackupError::RusqliteError)?;
+
+        Self::with_connection(connection, encryption)
+    }
+
+    /// Create a store on top of an already opened database, migrating it to the latest schema
+    pub fn with_connection(
+        mut connection: Connection,
+        encryption: Option<Encryption>,
+    ) -> BackupResult<Self> {
+        migrations::migrate(&mut connection).map_err(BackupError::RusqliteError)?;
+
+        let mut store = Self {
+            connection,
+            encryption,
+        };
+
+        if store.encryption.is_some() {
+            let encrypted = store.encrypt_save_codes(false)?;
+
+            if encrypted > 0 {
+                info!(
+                    "Encrypted {} backups stored before encryption was enabled",
+                    encrypted
+                );
+            }
+        }
+
+        store.backfill_metrics()?;
+
+        Ok(store)
+    }
+
+    /// Populate the metrics of backups stored before they were tracked
+    ///
+    /// Backups that cannot be decrypted are skipped, reading them reports the error instead.
+    /// Saves that cannot be decoded are flagged, so they are not decoded again on every start.
+    fn backfill_metrics(&mut self) -> BackupResult<()> {
+        let backups = self
+            .connection
+            .prepare(include_str!("../sql/get_backups_without_metrics.sql"))
+            .map_err(BackupError::RusqliteError)?
+            .query_map([], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })
+            .map_err(BackupError::RusqliteError)?
+            .collect::<Result<Vec<(i64, Vec<u8>, String, DateTime<Utc>)>, _>>()
+            .map_err(BackupError::RusqliteError)?;
+
+        let encryption = self.encryption.as_ref();
+        let transaction = self
+            .connection
+            .transaction()
+            .map_err(BackupError::RusqliteError)?;
+
+        for (id, save_code, content_hash, saved_at) in backups {
+            let aad = row_aad(id, &saved_at, &content_hash);
+            let compressed = match encryption::decrypt(encryption, &save_code, &aad) {
+                Ok(compressed) => compressed,
+                Err(_) => continue,
+            };
+
+            let save = decompress_save_code(&compressed)
+                .ok()
+                .and_then(|save_code| SaveData::decode(&save_code).ok());
+
+            let metrics = match save {
+                Some(save) => BackupMetrics::from_save(&save),
+                None => {
+                    transaction
+                        .execute(
+                            include_str!("../sql/set_backup_metrics_unavailable.sql"),
+                            params![id],
+                        )
+                        .map_err(BackupError::RusqliteError)?;
+
+                    continue;
+                }
+            };
+
+            transaction
+                .execute(
+                    include_str!("../sql/update_backup_metrics.sql"),
+                    params![
+                        id,
+                        metrics.cookies,
+                        metrics.cookies_baked,
+                        metrics.cookies_per_second,
+                        metrics.prestige,
+                        metrics.buildings,
+                        metrics.heavenly_chips
+                    ],
+                )
+                .map_err(BackupError::RusqliteError)?;
+        }
+
+        transaction.commit().map_err(BackupError::RusqliteError)?;
+
+        Ok(())
+    }
+
+    /// Encrypt save codes with the current key, returns how many were encrypted
+    ///
+    /// Only plain save codes are encrypted unless `rotate` is set, so that
+    /// backups stored before encryption was enabled are readable without
+    /// needing the old keys.
+    fn encrypt_save_codes(&mut self, rotate: bool) -> BackupResult<usize> {
+        let encryption = match &self.encryption {
+            Some(encryption) => encryption,
+            None => return Ok(0),
+        };
+
+        let save_codes = self
+            .connection
+            .prepare(include_str!("../sql/get_backup_save_codes.sql"))
+            .map_err(BackupError::RusqliteError)?
+            .query_map([], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })
+            .map_err(BackupError::RusqliteError)?
+            .collect::<Result<Vec<(i64, Vec<u8>, String, DateTime<Utc>)>, _>>()
+            .map_err(BackupError::RusqliteError)?;
+
+        let transaction = self
+            .connection
+            .transaction()
+            .map_err(BackupError::RusqliteError)?;
+
+        let mut encrypted = 0;
+
+        for (id, save_code, content_hash, saved_at) in save_codes {
+            let is_plain = !encryption::is_encrypted(&save_code);
+
+            if !is_plain && (!rotate || encryption.is_current(&save_code)) {
+                continue;
+            }
+
+            let aad = row_aad(id, &saved_at, &content_hash);
+
+            // Backups stored before encryption was enabled are only accepted here
+            let compressed = if is_plain {
+                save_code
+            } else {
+                encryption::decrypt(Some(encryption), &save_code, &aad)
+                    .map_err(BackupError::DecryptionFailed)?
+            };
+
+            transaction
+                .execute(
+                    include_str!("../sql/update_backup_save_code.sql"),
+                    params![id, encryption.encrypt(&compressed, &aad)],
+                )
+                .map_err(BackupError::RusqliteError)?;
+
+            encrypted += 1;
+        }
+
+        transaction.commit().map_err(BackupError::RusqliteError)?;
+
+        Ok(encrypted)
+    }
+
+    fn query_backups<P: rusqlite::Params>(
+        &mut self,
+        sql: &str,
+        params: P,
+    ) -> BackupResult<Vec<Backup>> {
+        let encryption = self.encryption.as_ref();
+
+        self.connection
+            .prepare(sql)
+            .map_err(BackupError::RusqliteError)?
+            .query_map(params, |row| backup_from_row(row, encryption))
+            .map_err(BackupError::RusqliteError)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(map_row_error)
+    }
+
+    fn query_backup<P: rusqlite::Params>(
+        &mut self,
+        sql: &str,
+        params: P,
+    ) -> BackupResult<Option<Backup>> {
+        let encryption = self.encryption.as_ref();
+
+        self.connection
+            .query_row(sql, params, |row| backup_from_row(row, encryption))
+            .optional()
+            .map_err(map_row_error)
+    }
+}
+
+impl BackupStore for SqliteStore {
+    fn insert(&mut self, backup: &Backup, content_hash: &str) -> BackupResult<i64> {
+        let metrics = backup.metrics;
+        let compressed = compress_save_code(&backup.save_code);
+
+        let transaction = self
+            .connection
+            .transaction()
+            .map_err(BackupError::RusqliteError)?;
+
+        // Encrypted save codes are bound to their id, which is only known once inserted
+        let save_code: &[u8] = match self.encryption {
+            Some(_) => &[],
+            None => &compressed,
+        };
+
+        transaction
+            .execute(
+                include_str!("../sql/insert_backup.sql"),
+                params![
+                    save_code,
+                    content_hash,
+                    backup.saved_at,
+                    metrics.map(|metrics| metrics.cookies),
+                    metrics.map(|metrics| metrics.cookies_baked),
+                    metrics.and_then(|metrics| metrics.cookies_per_second),
+                    metrics.map(|metrics| metrics.prestige),
+                    metrics.map(|metrics| metrics.buildings),
+                    metrics.map(|metrics| metrics.heavenly_chips)
+                ],
+            )
+            .map_err(BackupError::RusqliteError)?;
+
+        let id = transaction.last_insert_rowid();
+
+        if let Some(encryption) = &self.encryption {
+            let aad = row_aad(id, &backup.saved_at, content_hash);
+
+            transaction
+                .execute(
+                    include_str!("../sql/update_backup_save_code.sql"),
+                    params![id, encryption.encrypt(&compressed, &aad)],
+                )
+                .map_err(BackupError::RusqliteError)?;
+        }
+
+        transaction.commit().map_err(BackupError::RusqliteError)?;
+
+        Ok(id)
+    }
+
+    fn latest_content_hash(&mut self) -> BackupResult<Option<String>> {
+        self.connection
+            .query_row(
+                include_str!("../sql/get_latest_content_hash.sql"),
+                [],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(BackupError::RusqliteError)
+    }
+
+    fn latest(&mut self) -> BackupResult<Option<Backup>> {
+        self.query_backup(include_str!("../sql/get_latest_backup.sql"), [])
+    }
+
+    fn get(&mut self, id: i64) -> BackupResult<Option<Backup>> {
+        self.query_backup(include_str!("../sql/get_backup.sql"), params![id])
+    }
+
+    fn latest_before(&mut self, instant: DateTime<Utc>) -> BackupResult<Option<Backup>> {
+        self.query_backup(
+            include_str!("../sql/get_backup_before.sql"),
+            params![instant],
+        )
+    }
+
+    fn list(&mut self, offset: usize, limit: usize) -> BackupResult<Vec<Backup>> {
+        self.query_backups(
+            include_str!("../sql/get_backups_page.sql"),
+            params![limit, offset],
+        )
+    }
+
+    fn between(
+        &mut self,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> BackupResult<Vec<Backup>> {
+        self.query_backups(
+            include_str!("../sql/get_backups_between.sql"),
+            params![from, to],
+        )
+    }
+
+    fn count(&mut self) -> BackupResult<usize> {
+        self.connection
+            .query_row(include_str!("../sql/count_backups.sql"), [], |row| {
+                row.get(0)
+            })
+            .map_err(BackupError::RusqliteError)
+    }
+
+    fn get_by_label(&mut self, label: &str) -> BackupResult<Option<Backup>> {
+        self.query_backup(
+            include_str!("../sql/get_backup_by_label.sql"),
+            params![label],
+        )
+    }
+
+    fn pinned(&mut self) -> BackupResult<Vec<Backup>> {
+        self.query_backups(include_str!("../sql/get_pinned_backups.sql"), [])
+    }
+
+    fn set_label(&mut self, id: i64, label: Option<&str>) -> BackupResult<bool> {
+        let updated = self
+            .connection
+            .execute(
+                include_str!("../sql/set_backup_label.sql"),
+                params![id, label],
+            )
+            .map_err(BackupError::RusqliteError)?;
+
+        Ok(updated > 0)
+    }
+
+    fn unpinned_dates(&mut self) -> BackupResult<Vec<(i64, DateTime<Utc>)>> {
+        self.connection
+            .prepare(include_str!("../sql/get_unpinned_backup_dates.sql"))
+            .map_err(BackupError::RusqliteError)?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(BackupError::RusqliteError)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(BackupError::RusqliteError)
+    }
+
+    fn delete(&mut self, ids: &[i64]) -> BackupResult<()> {
+        let transaction = self
+            .connection
+            .transaction()
+            .map_err(BackupError::RusqliteError)?;
+
+        for id in ids {
+            transaction
+                .execute(include_str!("../sql/delete_backup.sql"), params![id])
+                .map_err(BackupError::RusqliteError)?;
+        }
+
+        transaction.commit().map_err(BackupError::RusqliteError)
+    }
+
+    fn rotate_key(&mut self) -> BackupResult<usize> {
+        if self.encryption.is_none() {
+            return Err(BackupError::EncryptionDisabled);
+        }
+
+        self.encrypt_save_codes(true)
+    }
+}
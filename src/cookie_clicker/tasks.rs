@@ -1,27 +1,98 @@
 use std::{sync::Arc, time::Duration};
 
-use log::{error, info};
-use tokio::sync::Mutex;
+use chrono::{DateTime, Utc};
+use log::{error, info, warn};
+use tokio::sync::{mpsc, Mutex};
 
-use super::CookieClicker;
+use super::{
+    backup::{Backup, BackupResult},
+    integrity::IntegrityChecker,
+    replication::{Replication, ReplicationError, ReplicationResult},
+    CookieClicker, GardenGoal, GrimoireMode, WrathPolicy,
+};
 
 pub type ConcurrentCookieClicker = Arc<Mutex<CookieClicker>>;
 
+/// Messages for the admin, sent by tasks running in the background
+pub type Notifications = mpsc::UnboundedSender<String>;
+
 const BACKUP_TASK_WAIT_SECONDS: u64 = 60;
+const AUTO_CLICK_TASK_WAIT_SECONDS: u64 = 1;
+const AUTO_BUY_TASK_WAIT_SECONDS: u64 = 30;
+const WRINKLER_TASK_WAIT_SECONDS: u64 = 60;
+const LUMP_TASK_WAIT_SECONDS: u64 = 60;
+/// Frenzies last over a minute, building specials half that
+const GRIMOIRE_TASK_WAIT_SECONDS: u64 = 5;
+const PANTHEON_TASK_WAIT_SECONDS: u64 = 5;
+/// Stock values change once a minute
+const MARKET_TASK_WAIT_SECONDS: u64 = 60;
+/// Plants take several minutes to grow at best
+const GARDEN_TASK_WAIT_SECONDS: u64 = 60;
+/// Golden cookies stay on screen for at least a dozen seconds
+const SHIMMER_TASK_WAIT_SECONDS: u64 = 2;
+const REPLICATION_TASK_WAIT_SECONDS: u64 = 300;
+const INTEGRITY_TASK_WAIT_SECONDS: u64 = 3600;
+/// How far back the first integrity check goes after a start
+const INTEGRITY_CHECK_HOURS: i64 = 24;
 
 pub struct CookieClickerTasks {
     cookie_clicker: ConcurrentCookieClicker,
+    notifications: Notifications,
 }
 
 impl CookieClickerTasks {
     /// Create new `CookieClickerTasks` instance
-    pub fn new(cookie_clicker: ConcurrentCookieClicker) -> Self {
-        Self { cookie_clicker }
+    pub fn new(cookie_clicker: ConcurrentCookieClicker, notifications: Notifications) -> Self {
+        Self {
+            cookie_clicker,
+            notifications,
+        }
     }
 
     /// Start tasks
     pub async fn start(self) {
-        tokio::spawn(async move { Self::backup_save_code_task(self.cookie_clicker).await });
+        let cookie_clicker = self.cookie_clicker.clone();
+        tokio::spawn(async move { Self::backup_save_code_task(cookie_clicker).await });
+
+        let cookie_clicker = self.cookie_clicker.clone();
+        tokio::spawn(async move { Self::auto_click_task(cookie_clicker).await });
+
+        let cookie_clicker = self.cookie_clicker.clone();
+        tokio::spawn(async move { Self::auto_buy_task(cookie_clicker).await });
+
+        let cookie_clicker = self.cookie_clicker.clone();
+        tokio::spawn(async move { Self::manage_wrinklers_task(cookie_clicker).await });
+
+        let cookie_clicker = self.cookie_clicker.clone();
+        let notifications = self.notifications.clone();
+        tokio::spawn(async move { Self::manage_lumps_task(cookie_clicker, notifications).await });
+
+        let cookie_clicker = self.cookie_clicker.clone();
+        let notifications = self.notifications.clone();
+        tokio::spawn(async move { Self::manage_garden_task(cookie_clicker, notifications).await });
+
+        let cookie_clicker = self.cookie_clicker.clone();
+        tokio::spawn(async move { Self::trade_stocks_task(cookie_clicker).await });
+
+        let cookie_clicker = self.cookie_clicker.clone();
+        tokio::spawn(async move { Self::cast_spells_task(cookie_clicker).await });
+
+        let cookie_clicker = self.cookie_clicker.clone();
+        tokio::spawn(async move { Self::manage_pantheon_task(cookie_clicker).await });
+
+        let cookie_clicker = self.cookie_clicker.clone();
+        let policy = WrathPolicy::from_env();
+        tokio::spawn(async move { Self::collect_shimmers_task(cookie_clicker, policy).await });
+
+        let cookie_clicker = self.cookie_clicker.clone();
+        let notifications = self.notifications.clone();
+        tokio::spawn(async move { Self::verify_backups_task(cookie_clicker, notifications).await });
+
+        if let Some(replication) = Replication::from_env() {
+            tokio::spawn(async move {
+                Self::replicate_backups_task(self.cookie_clicker, replication).await
+            });
+        }
     }
 
     /// Perform save code backup once in a while
@@ -38,10 +109,422 @@ impl CookieClickerTasks {
                 }
 
                 match cookie_clicker.backup_save_code().await {
-                    Ok(_) => info!("Back up done"),
+                    Ok(true) => info!("Back up done"),
+                    Ok(false) => info!("Save code unchanged since the latest backup, skipped"),
                     Err(error) => error!("There was an error while backing up: {:?}", error),
                 }
             }
         }
     }
+
+    /// Click the big cookie at the configured rate while auto-click is enabled
+    async fn auto_click_task(cookie_clicker: ConcurrentCookieClicker) {
+        loop {
+            tokio::time::sleep(Duration::from_secs(AUTO_CLICK_TASK_WAIT_SECONDS)).await;
+
+            let mut cookie_clicker = cookie_clicker.lock().await;
+
+            if !cookie_clicker.is_started() || !cookie_clicker.auto_click.enabled {
+                continue;
+            }
+
+            let clicks =
+                cookie_clicker.auto_click.clicks_per_second() * AUTO_CLICK_TASK_WAIT_SECONDS as u32;
+
+            if let Err(error) = cookie_clicker.click_big_cookie(clicks).await {
+                error!(
+                    "There was an error while clicking the big cookie: {:?}",
+                    error
+                );
+            }
+        }
+    }
+
+    /// Buy buildings and upgrades once in a while when auto-buy is enabled
+    async fn auto_buy_task(cookie_clicker: ConcurrentCookieClicker) {
+        loop {
+            tokio::time::sleep(Duration::from_secs(AUTO_BUY_TASK_WAIT_SECONDS)).await;
+
+            let mut cookie_clicker = cookie_clicker.lock().await;
+
+            if !cookie_clicker.is_started() || !cookie_clicker.auto_buy.enabled {
+                continue;
+            }
+
+            match cookie_clicker.auto_buy().await {
+                Ok(Some(purchase)) => info!(
+                    "Bought {} {} for {} cookies, +{} cookies per second",
+                    purchase.kind.as_str(),
+                    purchase.name,
+                    purchase.price,
+                    purchase.cps_gain
+                ),
+                Ok(None) => (),
+                Err(error) => error!("There was an error while buying: {:?}", error),
+            }
+        }
+    }
+
+    /// Pop wrinklers once in a while, according to the wrinkler policy
+    async fn manage_wrinklers_task(cookie_clicker: ConcurrentCookieClicker) {
+        loop {
+            tokio::time::sleep(Duration::from_secs(WRINKLER_TASK_WAIT_SECONDS)).await;
+
+            let mut cookie_clicker = cookie_clicker.lock().await;
+
+            if !cookie_clicker.is_started() {
+                continue;
+            }
+
+            match cookie_clicker.manage_wrinklers().await {
+                Ok(Some(pop)) => info!(
+                    "Popped {} wrinklers, reclaiming at least {} cookies",
+                    pop.wrinklers, pop.reclaimed
+                ),
+                Ok(None) => (),
+                Err(error) => error!("There was an error while popping wrinklers: {:?}", error),
+            }
+        }
+    }
+
+    /// Harvest ripe sugar lumps and spend them, notifying the admin about what was done
+    async fn manage_lumps_task(
+        cookie_clicker: ConcurrentCookieClicker,
+        notifications: Notifications,
+    ) {
+        loop {
+            tokio::time::sleep(Duration::from_secs(LUMP_TASK_WAIT_SECONDS)).await;
+
+            let mut cookie_clicker = cookie_clicker.lock().await;
+
+            if !cookie_clicker.is_started() {
+                continue;
+            }
+
+            match cookie_clicker.manage_lumps().await {
+                Ok(report) if report.harvested.is_none() && report.levels.is_empty() => (),
+                Ok(report) => {
+                    info!("{}", report);
+                    let _ = notifications.send(report.to_string());
+                }
+                Err(error) => error!("There was an error while managing sugar lumps: {:?}", error),
+            }
+        }
+    }
+
+    /// Tend the garden, notifying the admin about unlocked seeds and sacrifices
+    async fn manage_garden_task(
+        cookie_clicker: ConcurrentCookieClicker,
+        notifications: Notifications,
+    ) {
+        loop {
+            tokio::time::sleep(Duration::from_secs(GARDEN_TASK_WAIT_SECONDS)).await;
+
+            let mut cookie_clicker = cookie_clicker.lock().await;
+
+            if !cookie_clicker.is_started() || cookie_clicker.garden.goal == GardenGoal::Idle {
+                continue;
+            }
+
+            match cookie_clicker.manage_garden().await {
+                Ok(report) if report.is_notable() => {
+                    info!("{}", report);
+                    let _ = notifications.send(report.to_string());
+                }
+                Ok(report) if report.planted > 0 || report.harvested > 0 => info!("{}", report),
+                Ok(_) => (),
+                Err(error) => error!("There was an error while tending the garden: {:?}", error),
+            }
+        }
+    }
+
+    /// Buy and sell stocks when the trading bot is running
+    async fn trade_stocks_task(cookie_clicker: ConcurrentCookieClicker) {
+        loop {
+            tokio::time::sleep(Duration::from_secs(MARKET_TASK_WAIT_SECONDS)).await;
+
+            let mut cookie_clicker = cookie_clicker.lock().await;
+
+            if !cookie_clicker.is_started() || !cookie_clicker.trading.enabled {
+                continue;
+            }
+
+            match cookie_clicker.trade_stocks().await {
+                Ok(trades) => {
+                    for trade in trades {
+                        info!("{}", trade);
+                    }
+                }
+                Err(error) => error!("There was an error while trading stocks: {:?}", error),
+            }
+        }
+    }
+
+    /// Cast spells following the grimoire mode
+    async fn cast_spells_task(cookie_clicker: ConcurrentCookieClicker) {
+        loop {
+            tokio::time::sleep(Duration::from_secs(GRIMOIRE_TASK_WAIT_SECONDS)).await;
+
+            let mut cookie_clicker = cookie_clicker.lock().await;
+
+            if !cookie_clicker.is_started() || cookie_clicker.grimoire.mode == GrimoireMode::Off {
+                continue;
+            }
+
+            match cookie_clicker.cast_spells().await {
+                Ok(Some(cast)) => info!("{}", cast),
+                Ok(None) => (),
+                Err(error) => error!("There was an error while casting spells: {:?}", error),
+            }
+        }
+    }
+
+    /// Swap pantheon loadouts as combos start and end
+    async fn manage_pantheon_task(cookie_clicker: ConcurrentCookieClicker) {
+        loop {
+            tokio::time::sleep(Duration::from_secs(PANTHEON_TASK_WAIT_SECONDS)).await;
+
+            let mut cookie_clicker = cookie_clicker.lock().await;
+
+            if !cookie_clicker.is_started() || !cookie_clicker.pantheon.enabled {
+                continue;
+            }
+
+            match cookie_clicker.manage_pantheon().await {
+                Ok(Some(report)) => info!("{}", report),
+                Ok(None) => (),
+                Err(error) => error!(
+                    "There was an error while managing the pantheon: {:?}",
+                    error
+                ),
+            }
+        }
+    }
+
+    /// Pop golden cookies and reindeer as soon as they appear
+    async fn collect_shimmers_task(cookie_clicker: ConcurrentCookieClicker, policy: WrathPolicy) {
+        loop {
+            tokio::time::sleep(Duration::from_secs(SHIMMER_TASK_WAIT_SECONDS)).await;
+
+            let mut cookie_clicker = cookie_clicker.lock().await;
+
+            if !cookie_clicker.is_started() {
+                continue;
+            }
+
+            match cookie_clicker.collect_shimmers(policy).await {
+                Ok(collected) => {
+                    for shimmer in collected {
+                        info!(
+                            "Collected a {} shimmer: {}",
+                            shimmer.kind.as_str(),
+                            shimmer.effect
+                        );
+                    }
+                }
+                Err(error) => error!("There was an error while collecting shimmers: {:?}", error),
+            }
+        }
+    }
+
+    /// Upload new and merged backups to the replication bucket once in a while
+    async fn replicate_backups_task(
+        cookie_clicker: ConcurrentCookieClicker,
+        replication: Replication,
+    ) {
+        let mut merged = vec![];
+
+        let mut last_replicated = loop {
+            match Self::seed_backups(&cookie_clicker, &replication).await {
+                Ok(last_replicated) => break last_replicated,
+                Err(error) => error!("Cannot reach the replication bucket: {:?}", error),
+            }
+
+            tokio::time::sleep(Duration::from_secs(REPLICATION_TASK_WAIT_SECONDS)).await;
+        };
+
+        loop {
+            tokio::time::sleep(Duration::from_secs(REPLICATION_TASK_WAIT_SECONDS)).await;
+
+            match Self::replicate_backups(
+                &cookie_clicker,
+                &replication,
+                &mut last_replicated,
+                &mut merged,
+            )
+            .await
+            {
+                Ok(0) => (),
+                Ok(replicated) => info!("Replicated {} backups", replicated),
+                Err(error) => error!("There was an error while replicating backups: {:?}", error),
+            }
+        }
+    }
+
+    /// Restore the replicated backups into an empty store, or upload the stored
+    /// backups missing from the bucket
+    ///
+    /// Returns the date of the newest replicated backup, where replication resumes from.
+    async fn seed_backups(
+        cookie_clicker: &ConcurrentCookieClicker,
+        replication: &Replication,
+    ) -> ReplicationResult<Option<DateTime<Utc>>> {
+        let stored = cookie_clicker
+            .lock()
+            .await
+            .backups
+            .since(None)
+            .map_err(ReplicationError::BackupError)?;
+
+        if !stored.is_empty() {
+            let uploaded = replication.upload_missing(&stored).await?;
+
+            if uploaded > 0 {
+                info!(
+                    "Replicated {} backups missing from the replication bucket",
+                    uploaded
+                );
+            }
+
+            return Ok(stored.last().map(|backup| backup.saved_at));
+        }
+
+        let replicated = replication.download().await?;
+        let last_replicated = replicated.last().map(|backup| backup.saved_at);
+
+        let mut cookie_clicker = cookie_clicker.lock().await;
+        let backups = &mut cookie_clicker.backups;
+
+        // A backup may have been taken while downloading
+        if backups.count().map_err(ReplicationError::BackupError)? > 0 {
+            return Ok(last_replicated);
+        }
+
+        let mut restored = 0;
+
+        for backup in replicated {
+            if backups
+                .import(backup)
+                .map_err(ReplicationError::BackupError)?
+            {
+                restored += 1;
+            }
+        }
+
+        if restored > 0 {
+            info!("Restored {} backups from the replication bucket", restored);
+        }
+
+        Ok(last_replicated)
+    }
+
+    /// Upload the backups taken after `last_replicated` and the ones merged since
+    /// the previous run, returns how many were uploaded
+    ///
+    /// Merged backups stay in `merged` until they are uploaded.
+    async fn replicate_backups(
+        cookie_clicker: &ConcurrentCookieClicker,
+        replication: &Replication,
+        last_replicated: &mut Option<DateTime<Utc>>,
+        merged: &mut Vec<Backup>,
+    ) -> ReplicationResult<usize> {
+        let backups = {
+            let backups = &mut cookie_clicker.lock().await.backups;
+            merged.extend(
+                backups
+                    .take_merged()
+                    .map_err(ReplicationError::BackupError)?,
+            );
+
+            backups
+                .since(*last_replicated)
+                .map_err(ReplicationError::BackupError)?
+        };
+
+        let mut uploaded = 0;
+
+        if !merged.is_empty() {
+            uploaded += replication.upload_missing(merged).await?;
+            merged.clear();
+        }
+
+        for backup in &backups {
+            replication.upload(backup).await?;
+            *last_replicated = Some(backup.saved_at);
+        }
+
+        Ok(uploaded + backups.len())
+    }
+
+    /// Check the backups taken since the previous run once in a while, and
+    /// notify the admin about the ones that look broken
+    async fn verify_backups_task(
+        cookie_clicker: ConcurrentCookieClicker,
+        notifications: Notifications,
+    ) {
+        let mut checker = IntegrityChecker::default();
+        let mut last_verified = None;
+
+        loop {
+            tokio::time::sleep(Duration::from_secs(INTEGRITY_TASK_WAIT_SECONDS)).await;
+
+            match Self::verify_backups(&cookie_clicker, &mut checker, &mut last_verified).await {
+                Ok(broken) if broken.is_empty() => info!("Backups verified"),
+                Ok(broken) => {
+                    warn!("Found {} broken backups", broken.len());
+
+                    let message = format!(
+                        "Warning: {} recent backups look broken\n{}",
+                        broken.len(),
+                        broken.join("\n")
+                    );
+                    let _ = notifications.send(message);
+                }
+                Err(error) => error!("There was an error while verifying backups: {:?}", error),
+            }
+        }
+    }
+
+    /// Check the backups taken after `last_verified`, returns a description of the broken ones
+    ///
+    /// The first run checks the last `INTEGRITY_CHECK_HOURS` hours.
+    async fn verify_backups(
+        cookie_clicker: &ConcurrentCookieClicker,
+        checker: &mut IntegrityChecker,
+        last_verified: &mut Option<DateTime<Utc>>,
+    ) -> BackupResult<Vec<String>> {
+        let backups = {
+            let backups = &mut cookie_clicker.lock().await.backups;
+
+            if last_verified.is_none() {
+                let start = Utc::now() - chrono::Duration::hours(INTEGRITY_CHECK_HOURS);
+
+                // Compare the first backup to the one before it
+                if let Some(previous) = backups.latest_before(start)? {
+                    checker.check(&previous);
+                }
+
+                *last_verified = Some(start);
+            }
+
+            backups.since(*last_verified)?
+        };
+
+        let mut broken = vec![];
+
+        for backup in &backups {
+            if let Some(issue) = checker.check(backup) {
+                broken.push(format!(
+                    "#{} {}: {}",
+                    backup.id.unwrap_or_default(),
+                    backup.saved_at_local().format("%Y-%m-%d %H:%M:%S"),
+                    issue
+                ));
+            }
+
+            *last_verified = Some(backup.saved_at);
+        }
+
+        Ok(broken)
+    }
 }
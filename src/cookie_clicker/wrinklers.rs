@@ -0,0 +1,204 @@
+use std::{env, fmt, str::FromStr};
+
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+
+use super::settings::env_switch;
+
+/// Wrinkler feeding on the big cookie
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Wrinkler {
+    pub id: i64,
+    /// Cookies sucked so far, given back with a bonus when popped
+    pub sucked: f64,
+    pub shiny: bool,
+}
+
+impl Wrinkler {
+    /// Parse an element of `Game.wrinklers` reduced to its `id`, `sucked` and `type` fields
+    pub fn from_json(wrinkler: &Value) -> Option<Self> {
+        Some(Self {
+            id: wrinkler["id"].as_i64()?,
+            sucked: wrinkler["sucked"].as_f64()?,
+            shiny: wrinkler["type"].as_i64()? == 1,
+        })
+    }
+}
+
+/// Wrinklers popped at once
+#[derive(Debug, Clone, PartialEq)]
+pub struct WrinklerPop {
+    pub wrinklers: usize,
+    /// Cookies the game gave back for the popped wrinklers
+    pub reclaimed: f64,
+    pub popped_at: DateTime<Utc>,
+}
+
+/// When to pop wrinklers
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WrinklerPolicy {
+    Never,
+    /// When the bank holds less than a number of hours of production
+    LowBank(f64),
+    /// When wrinklers sucked more than a number of hours of production
+    Sucked(f64),
+}
+
+impl FromStr for WrinklerPolicy {
+    type Err = ();
+
+    /// Parse `never`, `low_bank:<hours>` or `sucked:<hours>`
+    fn from_str(policy: &str) -> Result<Self, Self::Err> {
+        let hours = |hours: &str| {
+            hours
+                .parse()
+                .ok()
+                .filter(|hours: &f64| hours.is_finite() && *hours >= 0.0)
+                .ok_or(())
+        };
+
+        match policy.split_once(':') {
+            None if policy == "never" => Ok(Self::Never),
+            Some(("low_bank", value)) => Ok(Self::LowBank(hours(value)?)),
+            Some(("sucked", value)) => Ok(Self::Sucked(hours(value)?)),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for WrinklerPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Never => write!(f, "never"),
+            Self::LowBank(hours) => write!(f, "low_bank:{}", hours),
+            Self::Sucked(hours) => write!(f, "sucked:{}", hours),
+        }
+    }
+}
+
+/// Settings of the wrinkler task
+#[derive(Debug, Clone, PartialEq)]
+pub struct WrinklerManagement {
+    pub policy: WrinklerPolicy,
+    /// Shiny wrinklers are rare, players usually keep them for the achievement
+    pub keep_shiny: bool,
+}
+
+impl WrinklerManagement {
+    /// Load the settings from env `WRINKLER_POLICY` and `WRINKLER_KEEP_SHINY` (`on` or `off`)
+    pub fn from_env() -> Self {
+        let policy = match env::var("WRINKLER_POLICY") {
+            Ok(policy) => policy.parse().expect("Invalid env WRINKLER_POLICY"),
+            Err(_) => WrinklerPolicy::Never,
+        };
+
+        let keep_shiny = env_switch("WRINKLER_KEEP_SHINY", true);
+
+        Self { policy, keep_shiny }
+    }
+
+    /// Select the wrinklers to pop given the bank and the production
+    pub fn to_pop<'a>(
+        &self,
+        wrinklers: &'a [Wrinkler],
+        cookies: f64,
+        cps: f64,
+    ) -> Vec<&'a Wrinkler> {
+        let candidates: Vec<&Wrinkler> = wrinklers
+            .iter()
+            .filter(|wrinkler| !(self.keep_shiny && wrinkler.shiny))
+            .collect();
+
+        let sucked: f64 = candidates.iter().map(|wrinkler| wrinkler.sucked).sum();
+
+        let pop = match self.policy {
+            WrinklerPolicy::Never => false,
+            WrinklerPolicy::LowBank(hours) => cookies < hours * 3600.0 * cps,
+            WrinklerPolicy::Sucked(hours) => sucked > hours * 3600.0 * cps,
+        };
+
+        if pop {
+            candidates
+        } else {
+            vec![]
+        }
+    }
+}
+
+impl fmt::Display for WrinklerManagement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "popping {}, {} shiny wrinklers",
+            self.policy,
+            if self.keep_shiny {
+                "keeping"
+            } else {
+                "popping"
+            }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WRINKLERS: [Wrinkler; 3] = [
+        Wrinkler {
+            id: 0,
+            sucked: 3600.0,
+            shiny: false,
+        },
+        Wrinkler {
+            id: 1,
+            sucked: 3600.0,
+            shiny: false,
+        },
+        Wrinkler {
+            id: 2,
+            sucked: 7200.0,
+            shiny: true,
+        },
+    ];
+
+    fn popped(management: &WrinklerManagement, cookies: f64) -> Vec<i64> {
+        management
+            .to_pop(&WRINKLERS, cookies, 1.0)
+            .iter()
+            .map(|wrinkler| wrinkler.id)
+            .collect()
+    }
+
+    #[test]
+    fn pop_when_sucked_enough() {
+        let mut management = WrinklerManagement {
+            policy: "sucked:2".parse().unwrap(),
+            keep_shiny: true,
+        };
+
+        // Shiny wrinklers don't count when they are kept
+        assert_eq!(popped(&management, 0.0), Vec::<i64>::new());
+
+        management.keep_shiny = false;
+        assert_eq!(popped(&management, 0.0), [0, 1, 2]);
+
+        management.policy = WrinklerPolicy::Never;
+        assert_eq!(popped(&management, 0.0), Vec::<i64>::new());
+    }
+
+    #[test]
+    fn pop_when_bank_is_low() {
+        let management = WrinklerManagement {
+            policy: "low_bank:1".parse().unwrap(),
+            keep_shiny: true,
+        };
+
+        assert_eq!(popped(&management, 3000.0), [0, 1]);
+        assert_eq!(popped(&management, 4000.0), Vec::<i64>::new());
+
+        assert_eq!("never".parse(), Ok(WrinklerPolicy::Never));
+        assert_eq!("sucked:-1".parse::<WrinklerPolicy>(), Err(()));
+        assert_eq!("always".parse::<WrinklerPolicy>(), Err(()));
+    }
+}
@@ -1,10 +1,18 @@
 use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
 use log::info;
 use telegram_bot::{InputFileUpload, SendDocument, SendMessage};
 
-use crate::cookie_clicker::CookieClickerError;
+use crate::cookie_clicker::{
+    beautify, export_archive, import_archive, normalize_save_code, parse_ratio, timezone,
+    ArchiveError, Backup, CookieClickerError, Encryption, GardenGoal, Loadout, LumpPlanner,
+    SaveDiff, MAX_CLICKS_PER_SECOND,
+};
 
-use super::CommandData;
+use super::{
+    time_expression::parse_time_expression, CommandData, DocumentContents, DocumentContentsError,
+};
 
 #[derive(Debug)]
 pub enum CommandHandlerError {
@@ -14,10 +22,21 @@ pub enum CommandHandlerError {
     InstanceNotStarted,
     InstanceAlreadyStarted,
     NoBackupsFound,
+    BackupNotFound(i64),
+    LabelNotFound(String),
+    NoBackupBefore(String),
+    InvalidSaveCode(String),
+    InvalidArgument(String),
+    DocumentError(DocumentContentsError),
+    ArchiveError(ArchiveError),
 }
 
 type CommandHandlerResult = Result<(), CommandHandlerError>;
 
+const BACKUPS_PAGE_SIZE: usize = 10;
+const SHIMMERS_COUNT: usize = 10;
+const PURCHASES_COUNT: usize = 10;
+
 pub async fn handle_command(command_data: CommandData) -> CommandHandlerResult {
     let message = command_data.message;
 
@@ -49,7 +68,25 @@ pub async fn handle_command(command_data: CommandData) -> CommandHandlerResult {
         "/resume" => command_resume(command_data).await,
         "/screenshot" => command_screenshot(command_data).await,
         "/details" => command_details(command_data).await,
+        "/autoclick" => command_autoclick(command_data).await,
+        "/autobuy" => command_autobuy(command_data).await,
+        "/shimmers" => command_shimmers(command_data).await,
+        "/wrinklers" => command_wrinklers(command_data).await,
+        "/lumps" => command_lumps(command_data).await,
+        "/garden" => command_garden(command_data).await,
+        "/market" => command_market(command_data).await,
+        "/pantheon" => command_pantheon(command_data).await,
         "/backup" => command_backup(command_data).await,
+        "/retention" => command_retention(command_data).await,
+        "/backups" => command_backups(command_data).await,
+        "/backup_info" => command_backup_info(command_data).await,
+        "/diff" => command_diff(command_data).await,
+        "/pin" => command_pin(command_data).await,
+        "/unpin" => command_unpin(command_data).await,
+        "/pins" => command_pins(command_data).await,
+        "/rotate_key" => command_rotate_key(command_data).await,
+        "/export_backups" => command_export_backups(command_data).await,
+        "/import_backups" => command_import_backups(command_data).await,
         "/stop" => command_stop(command_data).await,
         _ => Err(CommandHandlerError::InvalidCommand),
     }
@@ -62,6 +99,10 @@ async fn command_start(command_data: CommandData) -> CommandHandlerResult {
         return Err(CommandHandlerError::InstanceAlreadyStarted);
     }
 
+    // Reject malformed saves before spending minutes booting a browser
+    let save_code = normalize_save_code(&command_data.message)
+        .map_err(|error| CommandHandlerError::InvalidSaveCode(error.to_string()))?;
+
     command_data
         .api
         .send(SendMessage::new(
@@ -73,7 +114,7 @@ async fn command_start(command_data: CommandData) -> CommandHandlerResult {
 
     // Start game
     cookie_clicker
-        .start(command_data.message)
+        .start(save_code)
         .await
         .map_err(CommandHandlerError::CookieClicker)?;
 
@@ -94,15 +135,50 @@ async fn command_resume(command_data: CommandData) -> CommandHandlerResult {
         return Err(CommandHandlerError::InstanceAlreadyStarted);
     }
 
-    let backup = cookie_clicker
+    let backup = if command_data.message.is_empty() {
+        cookie_clicker
+            .backups
+            .latest_backup()
+            .map_err(CookieClickerError::BackupError)
+            .map_err(CommandHandlerError::CookieClicker)?
+            .ok_or(CommandHandlerError::NoBackupsFound)?
+    } else if let Ok(id) = parse_backup_id(&command_data.message) {
+        cookie_clicker
+            .backups
+            .get(id)
+            .map_err(CookieClickerError::BackupError)
+            .map_err(CommandHandlerError::CookieClicker)?
+            .ok_or(CommandHandlerError::BackupNotFound(id))?
+    } else if let Some(backup) = cookie_clicker
         .backups
-        .latest_backup()
+        .get_by_label(command_data.message.trim())
         .map_err(CookieClickerError::BackupError)
-        .map_err(CommandHandlerError::CookieClicker)?;
+        .map_err(CommandHandlerError::CookieClicker)?
+    {
+        backup
+    } else {
+        // Restore the state the game was in at a given point in time
+        let now = Utc::now().with_timezone(&timezone());
+        let instant = parse_time_expression(&command_data.message, now).ok_or_else(|| {
+            CommandHandlerError::InvalidArgument(format!(
+                "{} is neither a backup id, a label nor a point in time",
+                command_data.message
+            ))
+        })?;
 
-    let backup = match backup {
-        Some(backup) => backup,
-        None => return Err(CommandHandlerError::NoBackupsFound),
+        cookie_clicker
+            .backups
+            .latest_before(instant)
+            .map_err(CookieClickerError::BackupError)
+            .map_err(CommandHandlerError::CookieClicker)?
+            .ok_or_else(|| {
+                CommandHandlerError::NoBackupBefore(
+                    instant
+                        .with_timezone(&timezone())
+                        .format("%Y-%m-%d %H:%M:%S")
+                        .to_string(),
+                )
+            })?
     };
 
     let save_code = backup.save_code.to_owned();
@@ -196,9 +272,177 @@ async fn command_details(command_data: CommandData) -> CommandHandlerResult {
         .await
         .map_err(CommandHandlerError::CookieClicker)?;
 
+    let mut message = format!(
+        "You have {} cookies and currently producing {} cookies per hour\nAuto-click: {}\nGrimoire: {}",
+        cookies_count_beautified,
+        cookies_per_hour_beautified,
+        cookie_clicker.auto_click,
+        cookie_clicker.grimoire.mode
+    );
+
+    let grimoire = cookie_clicker
+        .get_grimoire_state()
+        .await
+        .map_err(CommandHandlerError::CookieClicker)?;
+
+    if let Some(grimoire) = grimoire {
+        message.push_str(&format!(
+            ", {:.0}/{:.0} magic",
+            grimoire.magic, grimoire.max_magic
+        ));
+    }
+
+    let casts = cookie_clicker
+        .history
+        .spell_casts_since(Utc::now() - chrono::Duration::days(1))
+        .map_err(CookieClickerError::HistoryError)
+        .map_err(CommandHandlerError::CookieClicker)?;
+
+    for (spell, count, backfires) in casts {
+        message.push_str(&format!(
+            "\n{} cast {} times in the last day, {} backfired",
+            spell, count, backfires
+        ));
+    }
+
+    command_data
+        .api
+        .send(SendMessage::new(command_data.chat_id, message))
+        .await
+        .map_err(CommandHandlerError::TelegramError)?;
+
+    Ok(())
+}
+
+/// Pause or resume auto-click with `on`/`off`, or change its rate
+async fn command_autoclick(command_data: CommandData) -> CommandHandlerResult {
+    let mut cookie_clicker = command_data.cookie_clicker.lock().await;
+    let auto_click = &mut cookie_clicker.auto_click;
+
+    match command_data.message.trim() {
+        "" => (),
+        "on" => auto_click.enabled = true,
+        "off" => auto_click.enabled = false,
+        argument => {
+            let valid = argument
+                .parse()
+                .is_ok_and(|clicks_per_second| auto_click.set_clicks_per_second(clicks_per_second));
+
+            if !valid {
+                return Err(CommandHandlerError::InvalidArgument(format!(
+                    "Usage: /autoclick [on|off|<clicks per second, at most {}>]",
+                    MAX_CLICKS_PER_SECOND
+                )));
+            }
+        }
+    }
+
+    let message = format!("Auto-click: {}", auto_click);
+
+    command_data
+        .api
+        .send(SendMessage::new(command_data.chat_id, message))
+        .await
+        .map_err(CommandHandlerError::TelegramError)?;
+
+    Ok(())
+}
+
+/// Pause or resume auto-buy with `on`/`off`, or change its reserve with `reserve <reserve>`
+async fn command_autobuy(command_data: CommandData) -> CommandHandlerResult {
+    let mut cookie_clicker = command_data.cookie_clicker.lock().await;
+
+    let arguments: Vec<&str> = command_data.message.split_whitespace().collect();
+
+    match arguments.as_slice() {
+        [] => (),
+        ["on"] => cookie_clicker.auto_buy.enabled = true,
+        ["off"] => cookie_clicker.auto_buy.enabled = false,
+        ["reserve", reserve] => {
+            cookie_clicker.auto_buy.reserve = reserve.parse().map_err(|_| {
+                CommandHandlerError::InvalidArgument(format!(
+                    "Invalid reserve {}, use none, lucky, lucky_frenzy or a number of seconds of production",
+                    reserve
+                ))
+            })?
+        }
+        _ => {
+            return Err(CommandHandlerError::InvalidArgument(
+                "Usage: /autobuy [on|off|reserve <reserve>]".to_string(),
+            ))
+        }
+    }
+
+    let purchases = cookie_clicker
+        .history
+        .recent_purchases(PURCHASES_COUNT)
+        .map_err(CookieClickerError::HistoryError)
+        .map_err(CommandHandlerError::CookieClicker)?;
+
+    let mut message = format!("Auto-buy: {}\n", cookie_clicker.auto_buy);
+
+    if !purchases.is_empty() {
+        message.push_str("\nLatest purchases:\n");
+
+        for purchase in &purchases {
+            message.push_str(&format!(
+                "{} {} for {} cookies, +{} cookies per second\n",
+                purchase
+                    .bought_at
+                    .with_timezone(&timezone())
+                    .format("%Y-%m-%d %H:%M:%S"),
+                purchase.name,
+                beautify(purchase.price),
+                beautify(purchase.cps_gain)
+            ));
+        }
+    }
+
+    command_data
+        .api
+        .send(SendMessage::new(command_data.chat_id, message))
+        .await
+        .map_err(CommandHandlerError::TelegramError)?;
+
+    Ok(())
+}
+
+/// Show the wrinkler policy and the cookies reclaimed in the last day, or change the policy
+async fn command_wrinklers(command_data: CommandData) -> CommandHandlerResult {
+    let mut cookie_clicker = command_data.cookie_clicker.lock().await;
+
+    let arguments: Vec<&str> = command_data.message.split_whitespace().collect();
+
+    match arguments.as_slice() {
+        [] => (),
+        ["keep_shiny", "on"] => cookie_clicker.wrinklers.keep_shiny = true,
+        ["keep_shiny", "off"] => cookie_clicker.wrinklers.keep_shiny = false,
+        [policy] => {
+            cookie_clicker.wrinklers.policy = policy.parse().map_err(|_| {
+                CommandHandlerError::InvalidArgument(format!(
+                    "Invalid policy {}, use never, low_bank:<hours> or sucked:<hours>",
+                    policy
+                ))
+            })?
+        }
+        _ => {
+            return Err(CommandHandlerError::InvalidArgument(
+                "Usage: /wrinklers [<policy>|keep_shiny on|keep_shiny off]".to_string(),
+            ))
+        }
+    }
+
+    let (popped, reclaimed) = cookie_clicker
+        .history
+        .wrinkler_pops_since(Utc::now() - chrono::Duration::days(1))
+        .map_err(CookieClickerError::HistoryError)
+        .map_err(CommandHandlerError::CookieClicker)?;
+
     let message = format!(
-        "You have {} cookies and currently producing {} cookies per hour",
-        cookies_count_beautified, cookies_per_hour_beautified
+        "Wrinklers: {}\n{} wrinklers popped in the last day, reclaiming at least {} cookies",
+        cookie_clicker.wrinklers,
+        popped,
+        beautify(reclaimed)
     );
 
     command_data
@@ -210,6 +454,305 @@ async fn command_details(command_data: CommandData) -> CommandHandlerResult {
     Ok(())
 }
 
+/// Show the sugar lumps and the level priority list, or change it with `/lumps <priority>`
+async fn command_lumps(command_data: CommandData) -> CommandHandlerResult {
+    let mut cookie_clicker = command_data.cookie_clicker.lock().await;
+
+    if !command_data.message.trim().is_empty() {
+        cookie_clicker.lump_planner = LumpPlanner::parse(&command_data.message).ok_or_else(|| {
+            CommandHandlerError::InvalidArgument(format!(
+                "Invalid priority {}, use a list of <building>:<level> such as Farm:1,Wizard tower:10",
+                command_data.message
+            ))
+        })?;
+    }
+
+    let mut message = format!("Level priority: {}\n", cookie_clicker.lump_planner);
+
+    if cookie_clicker.is_started() {
+        let state = cookie_clicker
+            .get_lump_state()
+            .await
+            .map_err(CommandHandlerError::CookieClicker)?;
+
+        match state {
+            Some(state) if state.is_ripe() => message.push_str(&format!(
+                "{} sugar lumps, the {} lump is ripe",
+                state.lumps, state.lump_type
+            )),
+            Some(state) => message.push_str(&format!(
+                "{} sugar lumps, the {} lump is ripe in {} minutes",
+                state.lumps,
+                state.lump_type,
+                (state.ripe_in / 60_000.0).ceil()
+            )),
+            None => message.push_str("Sugar lumps are not unlocked yet"),
+        }
+    }
+
+    command_data
+        .api
+        .send(SendMessage::new(command_data.chat_id, message))
+        .await
+        .map_err(CommandHandlerError::TelegramError)?;
+
+    Ok(())
+}
+
+/// Show the garden as the bot sees it, or change its settings with `/garden goal <goal>` and `/garden sacrifice on|off`
+async fn command_garden(command_data: CommandData) -> CommandHandlerResult {
+    let mut cookie_clicker = command_data.cookie_clicker.lock().await;
+
+    let arguments: Vec<&str> = command_data.message.split_whitespace().collect();
+
+    match arguments.as_slice() {
+        [] => (),
+        ["goal", goal] => {
+            cookie_clicker.garden.goal = goal.parse::<GardenGoal>().map_err(|_| {
+                CommandHandlerError::InvalidArgument(format!(
+                    "Invalid goal {}, use idle, unlock_seeds or bakeberries",
+                    goal
+                ))
+            })?
+        }
+        ["sacrifice", "on"] => cookie_clicker.garden.sacrifice = true,
+        ["sacrifice", "off"] => cookie_clicker.garden.sacrifice = false,
+        _ => {
+            return Err(CommandHandlerError::InvalidArgument(
+                "Usage: /garden [goal <goal>|sacrifice on|sacrifice off]".to_string(),
+            ))
+        }
+    }
+
+    let mut message = format!("Garden: {}\n", cookie_clicker.garden);
+
+    if cookie_clicker.is_started() {
+        let state = cookie_clicker
+            .get_garden_state()
+            .await
+            .map_err(CommandHandlerError::CookieClicker)?;
+
+        match state {
+            Some(state) => message.push_str(&format!("<pre>{}</pre>", state.render())),
+            None => message.push_str("The garden is not unlocked yet"),
+        }
+    }
+
+    let mut message = SendMessage::new(command_data.chat_id, message);
+    message.parse_mode(telegram_bot::ParseMode::Html);
+
+    command_data
+        .api
+        .send(message)
+        .await
+        .map_err(CommandHandlerError::TelegramError)?;
+
+    Ok(())
+}
+
+/// Show the stock positions and the realized profit, or change the strategy with `/market on|off`,
+/// `/market buy_below <ratio>` and `/market sell_above <ratio>`
+async fn command_market(command_data: CommandData) -> CommandHandlerResult {
+    let mut cookie_clicker = command_data.cookie_clicker.lock().await;
+
+    let arguments: Vec<&str> = command_data.message.split_whitespace().collect();
+
+    let ratio = |ratio: &str| {
+        parse_ratio(ratio).ok_or_else(|| {
+            CommandHandlerError::InvalidArgument(format!(
+                "Invalid ratio {}, use a positive ratio of the resting value such as 0.5",
+                ratio
+            ))
+        })
+    };
+
+    match arguments.as_slice() {
+        [] => (),
+        ["on"] => cookie_clicker.trading.enabled = true,
+        ["off"] => cookie_clicker.trading.enabled = false,
+        ["buy_below", value] => cookie_clicker.trading.buy_below = ratio(value)?,
+        ["sell_above", value] => cookie_clicker.trading.sell_above = ratio(value)?,
+        _ => {
+            return Err(CommandHandlerError::InvalidArgument(
+                "Usage: /market [on|off|buy_below <ratio>|sell_above <ratio>]".to_string(),
+            ))
+        }
+    }
+
+    let positions = cookie_clicker
+        .history
+        .market_positions()
+        .map_err(CookieClickerError::HistoryError)
+        .map_err(CommandHandlerError::CookieClicker)?;
+
+    let last_day = cookie_clicker
+        .history
+        .market_profit_since(Utc::now() - chrono::Duration::days(1))
+        .map_err(CookieClickerError::HistoryError)
+        .map_err(CommandHandlerError::CookieClicker)?;
+
+    let overall: f64 = positions.values().map(|position| position.realized).sum();
+
+    let mut message = format!(
+        "Trading: {}\nRealized profit: ${:.2} in the last day, ${:.2} overall\n",
+        cookie_clicker.trading, last_day, overall
+    );
+
+    let state = if cookie_clicker.is_started() {
+        cookie_clicker
+            .get_market_state()
+            .await
+            .map_err(CommandHandlerError::CookieClicker)?
+    } else {
+        None
+    };
+
+    match state {
+        Some(state) => {
+            for stock in state.stocks {
+                let mut line = format!(
+                    "\n{}: ${:.2} (resting ${:.2}), {}/{} held",
+                    stock.symbol, stock.value, stock.resting_value, stock.owned, stock.max_owned
+                );
+
+                if let Some(position) = positions
+                    .get(&stock.symbol)
+                    .filter(|position| position.shares > 0)
+                {
+                    line.push_str(&format!(
+                        ", {} bought by the bot at ${:.2}",
+                        position.shares,
+                        position.average_price()
+                    ));
+                }
+
+                message.push_str(&line);
+            }
+        }
+        None => {
+            for (symbol, position) in positions.iter().filter(|(_, position)| position.shares > 0) {
+                message.push_str(&format!(
+                    "\n{}: {} bought by the bot at ${:.2}",
+                    symbol,
+                    position.shares,
+                    position.average_price()
+                ));
+            }
+        }
+    }
+
+    command_data
+        .api
+        .send(SendMessage::new(command_data.chat_id, message))
+        .await
+        .map_err(CommandHandlerError::TelegramError)?;
+
+    Ok(())
+}
+
+/// Show the slotted gods and the loadouts, or change them with `/pantheon on|off`,
+/// `/pantheon idle <loadout>` and `/pantheon combo <loadout>`
+async fn command_pantheon(command_data: CommandData) -> CommandHandlerResult {
+    let mut cookie_clicker = command_data.cookie_clicker.lock().await;
+
+    let arguments: Vec<&str> = command_data.message.splitn(2, ' ').map(str::trim).collect();
+
+    let loadout = |loadout: &str| {
+        loadout.parse::<Loadout>().map_err(|_| {
+            CommandHandlerError::InvalidArgument(format!(
+                "Invalid loadout {}, use up to three known gods in slot order, by key or name, such as ruin,labor,mother",
+                loadout
+            ))
+        })
+    };
+
+    match arguments.as_slice() {
+        [""] => (),
+        ["on"] => cookie_clicker.pantheon.enabled = true,
+        ["off"] => cookie_clicker.pantheon.enabled = false,
+        ["idle", gods] => cookie_clicker.pantheon.idle = loadout(gods)?,
+        ["combo", gods] => cookie_clicker.pantheon.combo = loadout(gods)?,
+        _ => {
+            return Err(CommandHandlerError::InvalidArgument(
+                "Usage: /pantheon [on|off|idle <loadout>|combo <loadout>]".to_string(),
+            ))
+        }
+    }
+
+    let mut message = format!("Pantheon: {}", cookie_clicker.pantheon);
+
+    if cookie_clicker.is_started() {
+        let state = cookie_clicker
+            .get_pantheon_state()
+            .await
+            .map_err(CommandHandlerError::CookieClicker)?;
+
+        match state {
+            Some(state) => {
+                message.push_str(&format!("\nCurrent phase: {}\n{}", state.phase(), state))
+            }
+            None => message.push_str("\nThe pantheon is not unlocked yet"),
+        }
+    }
+
+    command_data
+        .api
+        .send(SendMessage::new(command_data.chat_id, message))
+        .await
+        .map_err(CommandHandlerError::TelegramError)?;
+
+    Ok(())
+}
+
+/// Summarize the golden cookies and reindeer collected in the last day
+async fn command_shimmers(command_data: CommandData) -> CommandHandlerResult {
+    let mut cookie_clicker = command_data.cookie_clicker.lock().await;
+    let history = &mut cookie_clicker.history;
+
+    let effects = history
+        .shimmer_effects_since(Utc::now() - chrono::Duration::days(1))
+        .map_err(CookieClickerError::HistoryError)
+        .map_err(CommandHandlerError::CookieClicker)?;
+    let recent = history
+        .recent_shimmers(SHIMMERS_COUNT)
+        .map_err(CookieClickerError::HistoryError)
+        .map_err(CommandHandlerError::CookieClicker)?;
+
+    let message = if recent.is_empty() {
+        "No golden cookie collected yet".to_string()
+    } else {
+        let mut message = "Effects collected in the last day:\n".to_string();
+
+        for (effect, count) in &effects {
+            message.push_str(&format!("{}: {}\n", effect, count));
+        }
+
+        message.push_str("\nLatest shimmers:\n");
+
+        for shimmer in &recent {
+            message.push_str(&format!(
+                "{} {} - {}\n",
+                shimmer
+                    .collected_at
+                    .with_timezone(&timezone())
+                    .format("%Y-%m-%d %H:%M:%S"),
+                shimmer.kind.as_str(),
+                shimmer.effect
+            ));
+        }
+
+        message
+    };
+
+    command_data
+        .api
+        .send(SendMessage::new(command_data.chat_id, message))
+        .await
+        .map_err(CommandHandlerError::TelegramError)?;
+
+    Ok(())
+}
+
 async fn command_backup(command_data: CommandData) -> CommandHandlerResult {
     let mut cookie_clicker = command_data.cookie_clicker.lock().await;
 
@@ -223,14 +766,534 @@ async fn command_backup(command_data: CommandData) -> CommandHandlerResult {
         .await
         .map_err(CommandHandlerError::TelegramError)?;
 
-    cookie_clicker
+    let stored = cookie_clicker
         .backup_save_code()
         .await
         .map_err(CommandHandlerError::CookieClicker)?;
 
+    let message = if stored {
+        "Backup complete"
+    } else {
+        "The save did not change since the latest backup, nothing to store"
+    };
+
+    command_data
+        .api
+        .send(SendMessage::new(command_data.chat_id, message))
+        .await
+        .map_err(CommandHandlerError::TelegramError)?;
+
+    Ok(())
+}
+
+async fn command_retention(command_data: CommandData) -> CommandHandlerResult {
+    let mut cookie_clicker = command_data.cookie_clicker.lock().await;
+
+    let backups_count = cookie_clicker
+        .backups
+        .count()
+        .map_err(CookieClickerError::BackupError)
+        .map_err(CommandHandlerError::CookieClicker)?;
+
+    let message = format!(
+        "Backups are kept {}, pinned backups are never pruned. There are currently {} backups stored",
+        cookie_clicker.backups.retention, backups_count
+    );
+
+    command_data
+        .api
+        .send(SendMessage::new(command_data.chat_id, message))
+        .await
+        .map_err(CommandHandlerError::TelegramError)?;
+
+    Ok(())
+}
+
+/// Encrypt every backup with the current key, after `BACKUP_ENCRYPTION_KEY` was changed
+async fn command_rotate_key(command_data: CommandData) -> CommandHandlerResult {
+    let mut cookie_clicker = command_data.cookie_clicker.lock().await;
+
+    let rotated = cookie_clicker
+        .backups
+        .rotate_key()
+        .map_err(CookieClickerError::BackupError)
+        .map_err(CommandHandlerError::CookieClicker)?;
+
+    let message = format!(
+        "{} backups encrypted with the current key, old keys can now be removed from BACKUP_ENCRYPTION_OLD_KEYS",
+        rotated
+    );
+
+    command_data
+        .api
+        .send(SendMessage::new(command_data.chat_id, message))
+        .await
+        .map_err(CommandHandlerError::TelegramError)?;
+
+    Ok(())
+}
+
+/// Bounds of a time range, `None` when unbounded
+type TimeRange = (Option<DateTime<Utc>>, Option<DateTime<Utc>>);
+
+/// Parse an optional `<from> [to <to>]` range of points in time
+fn parse_time_range(argument: &str) -> Result<TimeRange, CommandHandlerError> {
+    let argument = argument.trim();
+
+    if argument.is_empty() {
+        return Ok((None, None));
+    }
+
+    let now = Utc::now().with_timezone(&timezone());
+    let parse = |expression: &str| {
+        parse_time_expression(expression, now).ok_or_else(|| {
+            CommandHandlerError::InvalidArgument(format!("{} is not a point in time", expression))
+        })
+    };
+
+    match argument.split_once(" to ") {
+        Some((from, to)) => Ok((Some(parse(from)?), Some(parse(to)?))),
+        None => Ok((Some(parse(argument)?), None)),
+    }
+}
+
+/// Send every backup, or the ones of a time range, as a `.tar.gz` archive
+async fn command_export_backups(command_data: CommandData) -> CommandHandlerResult {
+    let (from, to) = parse_time_range(&command_data.message)?;
+
+    let backups = command_data
+        .cookie_clicker
+        .lock()
+        .await
+        .backups
+        .between(from, to)
+        .map_err(CookieClickerError::BackupError)
+        .map_err(CommandHandlerError::CookieClicker)?;
+
+    if backups.is_empty() {
+        return Err(CommandHandlerError::NoBackupsFound);
+    }
+
+    let archive = export_archive(&backups, Encryption::from_env().as_ref())
+        .map_err(CommandHandlerError::ArchiveError)?;
+    let file_name = format!(
+        "backups-{}.tar.gz",
+        Utc::now()
+            .with_timezone(&timezone())
+            .format("%Y%m%d-%H%M%S")
+    );
+
+    command_data
+        .api
+        .send(SendDocument::new(
+            command_data.chat_id,
+            InputFileUpload::with_data(Bytes::from(archive), file_name),
+        ))
+        .await
+        .map_err(CommandHandlerError::TelegramError)?;
+
+    let message = format!(
+        "{} backups exported, send the archive back to import them",
+        backups.len()
+    );
+
+    command_data
+        .api
+        .send(SendMessage::new(command_data.chat_id, message))
+        .await
+        .map_err(CommandHandlerError::TelegramError)?;
+
+    Ok(())
+}
+
+/// Merge the backups of an archive sent as a document
+async fn command_import_backups(command_data: CommandData) -> CommandHandlerResult {
+    let document = command_data.document.as_ref().ok_or_else(|| {
+        CommandHandlerError::InvalidArgument(
+            "Send an archive made by /export_backups as a document to import it".to_string(),
+        )
+    })?;
+
+    let archive = document
+        .to_bytes(&command_data.api)
+        .await
+        .map_err(CommandHandlerError::DocumentError)?;
+    let backups = import_archive(&archive, Encryption::from_env().as_ref())
+        .map_err(CommandHandlerError::ArchiveError)?;
+    let total = backups.len();
+
+    let (merged, pruned) = command_data
+        .cookie_clicker
+        .lock()
+        .await
+        .backups
+        .merge(backups)
+        .map_err(CookieClickerError::BackupError)
+        .map_err(CommandHandlerError::CookieClicker)?;
+
+    let mut message = format!(
+        "{} backups imported, {} were already stored",
+        merged - pruned,
+        total - merged
+    );
+
+    if pruned > 0 {
+        message.push_str(&format!(
+            ", {} were dropped as the retention policy expires them",
+            pruned
+        ));
+    }
+
+    command_data
+        .api
+        .send(SendMessage::new(command_data.chat_id, message))
+        .await
+        .map_err(CommandHandlerError::TelegramError)?;
+
+    Ok(())
+}
+
+/// Parse the id argument of backup commands
+fn parse_backup_id(argument: &str) -> Result<i64, CommandHandlerError> {
+    argument
+        .trim()
+        .trim_start_matches('#')
+        .parse()
+        .map_err(|_| {
+            CommandHandlerError::InvalidArgument(format!("Invalid backup id {}", argument))
+        })
+}
+
+/// One line summary of a backup
+fn backup_summary(backup: &Backup) -> String {
+    let cookies = match backup.metrics {
+        Some(metrics) => format!("{} cookies", beautify(metrics.cookies)),
+        None => "unreadable save".to_string(),
+    };
+
+    let label = match &backup.label {
+        Some(label) => format!(" [{}]", label),
+        None => "".to_string(),
+    };
+
+    format!(
+        "#{}{} {} - {}",
+        backup.id.unwrap_or_default(),
+        label,
+        backup.saved_at_local().format("%Y-%m-%d %H:%M:%S"),
+        cookies
+    )
+}
+
+async fn command_backups(command_data: CommandData) -> CommandHandlerResult {
+    let mut cookie_clicker = command_data.cookie_clicker.lock().await;
+
+    let page = if command_data.message.is_empty() {
+        1
+    } else {
+        command_data
+            .message
+            .trim()
+            .parse::<usize>()
+            .ok()
+            .filter(|page| *page > 0)
+            .ok_or_else(|| {
+                CommandHandlerError::InvalidArgument(format!(
+                    "Invalid page {}",
+                    command_data.message
+                ))
+            })?
+    };
+
+    let backups_count = cookie_clicker
+        .backups
+        .count()
+        .map_err(CookieClickerError::BackupError)
+        .map_err(CommandHandlerError::CookieClicker)?;
+
+    if backups_count == 0 {
+        return Err(CommandHandlerError::NoBackupsFound);
+    }
+
+    let pages_count = backups_count.div_ceil(BACKUPS_PAGE_SIZE);
+
+    let backups = cookie_clicker
+        .backups
+        .list(page - 1, BACKUPS_PAGE_SIZE)
+        .map_err(CookieClickerError::BackupError)
+        .map_err(CommandHandlerError::CookieClicker)?;
+
+    let mut message = format!("Backups, page {} of {}:\n", page, pages_count);
+
+    for backup in &backups {
+        message.push_str(&backup_summary(backup));
+        message.push('\n');
+    }
+
+    if page < pages_count {
+        message.push_str(&format!("\nUse /backups {} for older backups", page + 1));
+    }
+
     command_data
         .api
-        .send(SendMessage::new(command_data.chat_id, "Backup complete"))
+        .send(SendMessage::new(command_data.chat_id, message))
+        .await
+        .map_err(CommandHandlerError::TelegramError)?;
+
+    Ok(())
+}
+
+async fn command_backup_info(command_data: CommandData) -> CommandHandlerResult {
+    let mut cookie_clicker = command_data.cookie_clicker.lock().await;
+
+    let id = parse_backup_id(&command_data.message)?;
+
+    let backup = cookie_clicker
+        .backups
+        .get(id)
+        .map_err(CookieClickerError::BackupError)
+        .map_err(CommandHandlerError::CookieClicker)?
+        .ok_or(CommandHandlerError::BackupNotFound(id))?;
+
+    let save = backup
+        .save_data()
+        .map_err(|error| CommandHandlerError::InvalidSaveCode(error.to_string()))?;
+
+    let buildings: i64 = save.buildings.iter().map(|building| building.amount).sum();
+    let upgrades = save
+        .upgrades
+        .iter()
+        .filter(|upgrade| upgrade.bought)
+        .count();
+    let achievements = save.achievements.iter().filter(|won| **won).count();
+    let cookies_per_second = backup
+        .metrics
+        .and_then(|metrics| metrics.cookies_per_second)
+        .map(beautify)
+        .unwrap_or_else(|| "unknown".to_string());
+
+    let message = format!(
+        "Backup #{} taken at {}\n\
+        Bakery: {}\n\
+        Game version: {}\n\
+        Cookies: {}\n\
+        Cookies per second: {}\n\
+        Cookies baked this ascension: {}\n\
+        Cookies baked all time: {}\n\
+        Prestige level: {}\n\
+        Ascensions: {}\n\
+        Buildings: {}\n\
+        Upgrades bought: {}\n\
+        Achievements: {}\n\
+        Sugar lumps: {}\n\n\
+        Use /resume {} to restore it",
+        id,
+        backup.saved_at_local().format("%Y-%m-%d %H:%M:%S"),
+        save.run.bakery_name,
+        save.version,
+        beautify(save.stats.cookies),
+        cookies_per_second,
+        beautify(save.stats.cookies_earned),
+        beautify(save.stats.cookies_earned + save.stats.cookies_reset),
+        beautify(save.stats.prestige),
+        save.stats.resets,
+        buildings,
+        upgrades,
+        achievements,
+        save.stats.lumps.max(0.0),
+        id
+    );
+
+    command_data
+        .api
+        .send(SendMessage::new(command_data.chat_id, message))
+        .await
+        .map_err(CommandHandlerError::TelegramError)?;
+
+    Ok(())
+}
+
+/// Compare two backups without starting a browser session
+async fn command_diff(command_data: CommandData) -> CommandHandlerResult {
+    let mut cookie_clicker = command_data.cookie_clicker.lock().await;
+
+    let arguments: Vec<&str> = command_data.message.split_whitespace().collect();
+
+    let ids = match arguments.as_slice() {
+        [first, second] => [parse_backup_id(first)?, parse_backup_id(second)?],
+        _ => {
+            return Err(CommandHandlerError::InvalidArgument(
+                "Usage: /diff <id> <id>".to_string(),
+            ))
+        }
+    };
+
+    let mut backups = vec![];
+
+    for id in ids {
+        let backup = cookie_clicker
+            .backups
+            .get(id)
+            .map_err(CookieClickerError::BackupError)
+            .map_err(CommandHandlerError::CookieClicker)?
+            .ok_or(CommandHandlerError::BackupNotFound(id))?;
+
+        let save = backup
+            .save_data()
+            .map_err(|error| CommandHandlerError::InvalidSaveCode(error.to_string()))?;
+
+        backups.push((backup, save));
+    }
+
+    // Always describe what happened going forward in time
+    backups.sort_by_key(|(backup, _)| backup.saved_at_utc());
+    let (before, after) = (&backups[0], &backups[1]);
+
+    let message = format!(
+        "Changes from #{} ({}) to #{} ({}):\n{}",
+        before.0.id.unwrap_or_default(),
+        before.0.saved_at_local().format("%Y-%m-%d %H:%M:%S"),
+        after.0.id.unwrap_or_default(),
+        after.0.saved_at_local().format("%Y-%m-%d %H:%M:%S"),
+        SaveDiff::between(&before.1, &after.1)
+    );
+
+    command_data
+        .api
+        .send(SendMessage::new(command_data.chat_id, message))
+        .await
+        .map_err(CommandHandlerError::TelegramError)?;
+
+    Ok(())
+}
+
+/// Validate a backup label, which must not be mistaken for an id or a point in time
+///
+/// `/resume` looks labels up before time expressions, so a label such as
+/// `yesterday` would otherwise shadow them. Labels are part of file names in
+/// the filesystem store, so only letters, digits, `_` and `-` are allowed.
+fn parse_label(argument: &str) -> Result<&str, CommandHandlerError> {
+    let label = argument.trim();
+
+    if label.is_empty()
+        || !label
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        || label.parse::<i64>().is_ok()
+        || parse_time_expression(label, Utc::now().with_timezone(&Tz::UTC)).is_some()
+    {
+        return Err(CommandHandlerError::InvalidArgument(format!(
+            "Invalid label {:?}, labels are made of letters, digits, _ and - and are neither numbers nor points in time",
+            label
+        )));
+    }
+
+    Ok(label)
+}
+
+async fn command_pin(command_data: CommandData) -> CommandHandlerResult {
+    let mut cookie_clicker = command_data.cookie_clicker.lock().await;
+
+    // Either `/pin <label>` for the latest backup or `/pin <id> <label>`
+    let arguments: Vec<&str> = command_data.message.split_whitespace().collect();
+
+    let (id, label) = match arguments.as_slice() {
+        [label] => {
+            let latest_backup = cookie_clicker
+                .backups
+                .latest_backup()
+                .map_err(CookieClickerError::BackupError)
+                .map_err(CommandHandlerError::CookieClicker)?
+                .ok_or(CommandHandlerError::NoBackupsFound)?;
+
+            (latest_backup.id.unwrap_or_default(), parse_label(label)?)
+        }
+        [id, label] => (parse_backup_id(id)?, parse_label(label)?),
+        _ => {
+            return Err(CommandHandlerError::InvalidArgument(
+                "Usage: /pin [id] <label>".to_string(),
+            ))
+        }
+    };
+
+    let pinned = cookie_clicker
+        .backups
+        .pin(id, label)
+        .map_err(CookieClickerError::BackupError)
+        .map_err(CommandHandlerError::CookieClicker)?;
+
+    if !pinned {
+        return Err(CommandHandlerError::BackupNotFound(id));
+    }
+
+    let message = format!(
+        "Backup #{} pinned as {}, use /resume {} to restore it",
+        id, label, label
+    );
+
+    command_data
+        .api
+        .send(SendMessage::new(command_data.chat_id, message))
+        .await
+        .map_err(CommandHandlerError::TelegramError)?;
+
+    Ok(())
+}
+
+async fn command_unpin(command_data: CommandData) -> CommandHandlerResult {
+    let mut cookie_clicker = command_data.cookie_clicker.lock().await;
+
+    let label = parse_label(&command_data.message)?;
+
+    let unpinned = cookie_clicker
+        .backups
+        .unpin(label)
+        .map_err(CookieClickerError::BackupError)
+        .map_err(CommandHandlerError::CookieClicker)?;
+
+    if !unpinned {
+        return Err(CommandHandlerError::LabelNotFound(label.to_string()));
+    }
+
+    let message = format!(
+        "Backup {} unpinned, it will be pruned by the retention policy",
+        label
+    );
+
+    command_data
+        .api
+        .send(SendMessage::new(command_data.chat_id, message))
+        .await
+        .map_err(CommandHandlerError::TelegramError)?;
+
+    Ok(())
+}
+
+async fn command_pins(command_data: CommandData) -> CommandHandlerResult {
+    let mut cookie_clicker = command_data.cookie_clicker.lock().await;
+
+    let backups = cookie_clicker
+        .backups
+        .pinned()
+        .map_err(CookieClickerError::BackupError)
+        .map_err(CommandHandlerError::CookieClicker)?;
+
+    let message = if backups.is_empty() {
+        "There are no pinned backups, use /pin <label> to pin the latest one".to_string()
+    } else {
+        let mut message = "Pinned backups:\n".to_string();
+
+        for backup in &backups {
+            message.push_str(&backup_summary(backup));
+            message.push('\n');
+        }
+
+        message
+    };
+
+    command_data
+        .api
+        .send(SendMessage::new(command_data.chat_id, message))
         .await
         .map_err(CommandHandlerError::TelegramError)?;
 
@@ -275,3 +1338,40 @@ async fn command_stop(command_data: CommandData) -> CommandHandlerResult {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reject_invalid_labels() {
+        assert_eq!(parse_label(" pre-ascension ").unwrap(), "pre-ascension");
+        assert_eq!(parse_label("run_2").unwrap(), "run_2");
+        // Too far back to be a point in time
+        assert_eq!(parse_label("99999999dago").unwrap(), "99999999dago");
+
+        for label in [
+            "now",
+            "yesterday",
+            "Today",
+            "18:00",
+            "2023-04-01",
+            "42",
+            "#42",
+            "a/b",
+            "..",
+            "a\\b",
+            "two words",
+            "café",
+        ] {
+            assert!(
+                matches!(
+                    parse_label(label),
+                    Err(CommandHandlerError::InvalidArgument(_))
+                ),
+                "{}",
+                label
+            );
+        }
+    }
+}
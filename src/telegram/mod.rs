@@ -6,17 +6,20 @@ use log::{error, info, warn};
 use telegram_bot::{
     Api, ChatId, Document, GetFile, Message, MessageKind, MessageOrChannelPost, SendMessage, UserId,
 };
-use tokio::sync::Mutex;
+use tokio::sync::{mpsc, Mutex};
 
 use crate::cookie_clicker::{ConcurrentCookieClicker, CookieClicker, CookieClickerTasks};
 
 mod commands;
+mod time_expression;
 
 pub struct CommandData {
     api: Api,
     chat_id: ChatId,
     cookie_clicker: ConcurrentCookieClicker,
     message: String,
+    /// Document sent along with the command, such as a backup archive
+    document: Option<Document>,
 }
 
 impl CommandData {
@@ -25,12 +28,14 @@ impl CommandData {
         chat_id: ChatId,
         cookie_clicker: ConcurrentCookieClicker,
         message: String,
+        document: Option<Document>,
     ) -> Self {
         Self {
             api,
             chat_id,
             cookie_clicker,
             message,
+            document,
         }
     }
 }
@@ -76,41 +81,55 @@ fn is_user_admin(message: &Message) -> bool {
 }
 
 #[derive(Debug)]
-enum DocumentContentsToStringError {
+pub enum DocumentContentsError {
     TelegramError(telegram_bot::Error),
     CannotGetFileUrl,
     ReqwestError(reqwest::Error),
 }
 
 #[async_trait]
-trait DocumentContentsToString {
-    async fn to_string(&self, api: &Api) -> Result<String, DocumentContentsToStringError>;
+trait DocumentContents {
+    async fn to_bytes(&self, api: &Api) -> Result<Vec<u8>, DocumentContentsError>;
+
+    async fn to_string(&self, api: &Api) -> Result<String, DocumentContentsError> {
+        let bytes = self.to_bytes(api).await?;
+
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
 }
 
 #[async_trait]
-impl DocumentContentsToString for Document {
-    async fn to_string(&self, api: &Api) -> Result<String, DocumentContentsToStringError> {
+impl DocumentContents for Document {
+    async fn to_bytes(&self, api: &Api) -> Result<Vec<u8>, DocumentContentsError> {
         let file = api
             .send(GetFile::new(self))
             .await
-            .map_err(DocumentContentsToStringError::TelegramError)?;
+            .map_err(DocumentContentsError::TelegramError)?;
 
         let token = env::var("TELEGRAM_BOT_TOKEN").expect("Missing env TELEGRAM_BOT_TOKEN");
         let file_url = file
             .get_url(&token)
-            .ok_or(DocumentContentsToStringError::CannotGetFileUrl)?;
+            .ok_or(DocumentContentsError::CannotGetFileUrl)?;
 
         let body = reqwest::get(file_url)
             .await
-            .map_err(DocumentContentsToStringError::ReqwestError)?
-            .text()
+            .map_err(DocumentContentsError::ReqwestError)?
+            .bytes()
             .await
-            .map_err(DocumentContentsToStringError::ReqwestError)?;
+            .map_err(DocumentContentsError::ReqwestError)?;
 
-        Ok(body)
+        Ok(body.to_vec())
     }
 }
 
+/// Whether a document looks like an archive made by `/export_backups`
+fn is_backup_archive(document: &Document) -> bool {
+    document
+        .file_name
+        .as_ref()
+        .is_some_and(|file_name| file_name.ends_with(".tar.gz") || file_name.ends_with(".tgz"))
+}
+
 /// Main event handler loop
 pub async fn handle_events(api: &Api) {
     let cookie_clicker: ConcurrentCookieClicker = Arc::new(Mutex::new(
@@ -124,7 +143,20 @@ pub async fn handle_events(api: &Api) {
     {
         // Start async jobs
         let cookie_clicker = cookie_clicker.clone();
-        CookieClickerTasks::new(cookie_clicker).start().await;
+        let (notifications, mut receiver) = mpsc::unbounded_channel();
+        CookieClickerTasks::new(cookie_clicker, notifications)
+            .start()
+            .await;
+
+        // Forward task notifications to the admin
+        let api = api.clone();
+        tokio::spawn(async move {
+            while let Some(message) = receiver.recv().await {
+                if let Err(error) = send_admin_message(&api, message).await {
+                    error!("Cannot notify the admin: {:?}", error);
+                }
+            }
+        });
     }
 
     let mut stream = api.stream();
@@ -150,15 +182,23 @@ pub async fn handle_events(api: &Api) {
                 continue;
             }
 
+            let mut document = None;
+
             let message_text = if let MessageKind::Text { data, .. } = message.kind {
                 data
             } else if let MessageKind::Document { data, .. } = message.kind {
-                // Parse Document text as /start argument
-                match data.to_string(api).await {
-                    Ok(token) => format!("/start {}", token),
-                    Err(error) => {
-                        println!("Error while retrieving file: {:?}", error);
-                        continue;
+                if is_backup_archive(&data) {
+                    // Archives are downloaded by the command itself
+                    document = Some(data);
+                    "/import_backups".to_string()
+                } else {
+                    // Parse Document text as /start argument
+                    match data.to_string(api).await {
+                        Ok(token) => format!("/start {}", token),
+                        Err(error) => {
+                            println!("Error while retrieving file: {:?}", error);
+                            continue;
+                        }
                     }
                 }
             } else {
@@ -169,7 +209,8 @@ pub async fn handle_events(api: &Api) {
             let chat_id = message.chat.id();
             let cookie_clicker = cookie_clicker.clone();
 
-            let command_data = CommandData::new(api.clone(), chat_id, cookie_clicker, message_text);
+            let command_data =
+                CommandData::new(api.clone(), chat_id, cookie_clicker, message_text, document);
             command_task(api, command_data, chat_id).await;
         }
     }
@@ -0,0 +1,190 @@
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+
+/// Dates and times separated by a space are split in a day and a time instead
+const LOCAL_DATE_TIME_FORMATS: [&str; 2] = ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M"];
+
+/// Longest duration chrono can represent, in seconds
+const MAX_DURATION_SECONDS: i64 = i64::MAX / 1000;
+
+/// Parse a point in time such as `2h ago`, `yesterday 18:00`, `2023-04-01` or an RFC 3339 date
+///
+/// Dates and times without an offset are in the timezone of `now`, and dates
+/// without a time refer to midnight.
+pub fn parse_time_expression(expression: &str, now: DateTime<Tz>) -> Option<DateTime<Utc>> {
+    let expression = expression.trim();
+    let lowercase = expression.to_lowercase();
+
+    if lowercase == "now" {
+        return Some(now.with_timezone(&Utc));
+    }
+
+    if let Some(duration) = lowercase.strip_suffix("ago") {
+        return now
+            .with_timezone(&Utc)
+            .checked_sub_signed(parse_duration(duration)?);
+    }
+
+    if let Ok(date_time) = DateTime::parse_from_rfc3339(expression) {
+        return Some(date_time.with_timezone(&Utc));
+    }
+
+    let date_time = parse_local_date_time(expression, now)?;
+
+    now.timezone()
+        .from_local_datetime(&date_time)
+        .earliest()
+        .map(|date_time| date_time.with_timezone(&Utc))
+}
+
+/// Parse a date and time without an offset
+fn parse_local_date_time(expression: &str, now: DateTime<Tz>) -> Option<NaiveDateTime> {
+    let today = now.date_naive();
+
+    let (day, time) = match expression.split_once(' ') {
+        Some((day, time)) => (day, Some(time.trim())),
+        None => (expression, None),
+    };
+
+    let date = match day.to_lowercase().as_str() {
+        "today" => Some(today),
+        "yesterday" => today.pred_opt(),
+        day => NaiveDate::parse_from_str(day, "%Y-%m-%d").ok(),
+    };
+
+    match (date, time) {
+        (Some(date), None) => date.and_hms_opt(0, 0, 0),
+        (Some(date), Some(time)) => Some(date.and_time(parse_time(time)?)),
+        // A time alone refers to today
+        (None, None) => parse_time(day)
+            .map(|time| today.and_time(time))
+            .or_else(|| {
+                LOCAL_DATE_TIME_FORMATS
+                    .iter()
+                    .find_map(|format| NaiveDateTime::parse_from_str(day, format).ok())
+            }),
+        (None, Some(_)) => None,
+    }
+}
+
+fn parse_time(time: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(time, "%H:%M:%S")
+        .or_else(|_| NaiveTime::parse_from_str(time, "%H:%M"))
+        .ok()
+}
+
+/// Parse a duration such as `2h`, `1h 30m` or `3 days`
+fn parse_duration(duration: &str) -> Option<Duration> {
+    let mut seconds: i64 = 0;
+    let mut amount: Option<i64> = None;
+    let mut rest = duration.trim();
+
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+
+        if digits > 0 {
+            if amount.is_some() {
+                return None;
+            }
+
+            amount = Some(rest[..digits].parse().ok()?);
+            rest = rest[digits..].trim_start();
+            continue;
+        }
+
+        let unit_length = rest
+            .find(|c: char| !c.is_alphabetic())
+            .unwrap_or(rest.len());
+        let unit = &rest[..unit_length];
+
+        let unit: i64 = match unit {
+            "s" | "sec" | "secs" | "second" | "seconds" => 1,
+            "m" | "min" | "mins" | "minute" | "minutes" => 60,
+            "h" | "hour" | "hours" => 60 * 60,
+            "d" | "day" | "days" => 24 * 60 * 60,
+            "w" | "week" | "weeks" => 7 * 24 * 60 * 60,
+            _ => return None,
+        };
+
+        seconds = seconds.checked_add(unit.checked_mul(amount.take()?)?)?;
+        rest = rest[unit_length..].trim_start_matches([' ', ',']);
+    }
+
+    if amount.is_some() || seconds <= 0 || seconds > MAX_DURATION_SECONDS {
+        return None;
+    }
+
+    Some(Duration::seconds(seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono_tz::Europe::Paris;
+
+    use super::*;
+
+    fn parse(expression: &str) -> Option<String> {
+        let now = Paris.with_ymd_and_hms(2023, 4, 2, 12, 0, 0).unwrap();
+
+        parse_time_expression(expression, now).map(|instant| instant.to_rfc3339())
+    }
+
+    #[test]
+    fn parse_relative_expressions() {
+        assert_eq!(parse("now").unwrap(), "2023-04-02T10:00:00+00:00");
+        assert_eq!(parse("2h ago").unwrap(), "2023-04-02T08:00:00+00:00");
+        assert_eq!(
+            parse("1h, 30 minutes ago").unwrap(),
+            "2023-04-02T08:30:00+00:00"
+        );
+        assert_eq!(parse("3 days ago").unwrap(), "2023-03-30T10:00:00+00:00");
+        assert_eq!(
+            parse("yesterday 18:00").unwrap(),
+            "2023-04-01T16:00:00+00:00"
+        );
+        assert_eq!(parse("Today").unwrap(), "2023-04-01T22:00:00+00:00");
+
+        // A time alone refers to today
+        assert_eq!(parse("18:30").unwrap(), "2023-04-02T16:30:00+00:00");
+    }
+
+    #[test]
+    fn parse_dates() {
+        assert_eq!(parse("2023-03-25").unwrap(), "2023-03-24T23:00:00+00:00");
+        assert_eq!(
+            parse("2023-04-01 10:00").unwrap(),
+            "2023-04-01T08:00:00+00:00"
+        );
+        assert_eq!(
+            parse("2023-04-01T10:00:30").unwrap(),
+            "2023-04-01T08:00:30+00:00"
+        );
+        assert_eq!(
+            parse("2023-04-01T10:00:00-05:00").unwrap(),
+            "2023-04-01T15:00:00+00:00"
+        );
+    }
+
+    #[test]
+    fn reject_invalid_expressions() {
+        for expression in [
+            "",
+            "ago",
+            "0h ago",
+            "2 2h ago",
+            "2 fortnights ago",
+            "tomorrow",
+            "yesterday 25:00",
+            "18:00 yesterday",
+            "pre-ascension",
+            // Further back than a date can go
+            "99999999d ago",
+            "99999999999999999w ago",
+            "9223372036854775807s ago",
+        ] {
+            assert_eq!(parse(expression), None, "{}", expression);
+        }
+    }
+}